@@ -0,0 +1,158 @@
+//! Responses of the blend analysis endpoints
+use crate::robot::Status;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Outcome counts of one blended file
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct FileSummary {
+    /// Column title of the file.
+    #[schema(example = "output_a.xml")]
+    pub name: String,
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+/// Statuses of one test, one entry per file in column order
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct TestStatuses {
+    /// Long name of the test.
+    #[schema(example = "Tests.Login.Valid Login")]
+    pub test: String,
+    /// `null` where the test is missing from a file.
+    pub statuses: Vec<Option<Status>>,
+}
+
+/// Summary of a blend
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct BlendSummary {
+    /// Number of distinct tests over all files.
+    pub total_tests: usize,
+    pub files: Vec<FileSummary>,
+    /// Tests whose status is not the same in every file.
+    pub differing: Vec<TestStatuses>,
+    /// Tests failing in the last file that did not fail in the file before it.
+    pub newly_failing: Vec<String>,
+}
+
+/// Failure of a test in one run
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct RunFailure {
+    #[schema(example = "output_b.xml")]
+    pub run: String,
+    #[schema(example = "Element 'id=login' not visible after 5 seconds.")]
+    pub message: String,
+}
+
+/// A test that passed in some runs and failed in others
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct FlakyTest {
+    /// Long name of the test.
+    #[schema(example = "Tests.Login.Valid Login")]
+    pub test: String,
+    /// Runs in which the test passed or failed.
+    pub runs: usize,
+    pub passed: usize,
+    pub failed: usize,
+    /// Share of failing runs, between 0 and 1.
+    #[schema(example = 0.5)]
+    pub flakiness: f64,
+    pub failures: Vec<RunFailure>,
+}
+
+/// Failure message of a test that changed between two runs
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct MessageChange {
+    #[schema(example = "Tests.Login.Valid Login")]
+    pub test: String,
+    pub baseline: String,
+    pub current: String,
+}
+
+/// Run time of a test that grew by more than the threshold
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct DurationChange {
+    #[schema(example = "Tests.Login.Valid Login")]
+    pub test: String,
+    /// Seconds in the baseline run.
+    #[schema(example = 1.5)]
+    pub baseline: f64,
+    /// Seconds in the current run.
+    #[schema(example = 4.0)]
+    pub current: f64,
+}
+
+/// Differences between a baseline and a current run
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct Comparison {
+    /// Tests only in the current run.
+    pub added: Vec<String>,
+    /// Tests only in the baseline run.
+    pub removed: Vec<String>,
+    pub newly_failing: Vec<String>,
+    pub newly_passing: Vec<String>,
+    pub changed_messages: Vec<MessageChange>,
+    /// Tests that got slower by more than the threshold, the largest increase first.
+    pub duration_regressions: Vec<DurationChange>,
+}
+
+/// Run time statistics of a keyword over all runs, in seconds
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct KeywordTiming {
+    /// Keyword name, prefixed with its library when known.
+    #[schema(example = "SeleniumLibrary.Click Element")]
+    pub keyword: String,
+    pub calls: usize,
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+    pub p95: f64,
+}
+
+/// A test or keyword call with its run time in seconds
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct Slowest {
+    #[schema(example = "Tests.Login.Valid Login")]
+    pub name: String,
+    #[schema(example = 12.5)]
+    pub elapsed: f64,
+}
+
+/// Slowest tests and keyword calls of one file
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct FileTiming {
+    #[schema(example = "output_a.xml")]
+    pub file: String,
+    pub tests: Vec<Slowest>,
+    pub keywords: Vec<Slowest>,
+}
+
+/// Keyword timing analysis
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct Timing {
+    /// Keywords with the highest 95th percentile first.
+    pub keywords: Vec<KeywordTiming>,
+    pub files: Vec<FileTiming>,
+}
+
+/// A failing test in one file
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct FailedTest {
+    #[schema(example = "output_a.xml")]
+    pub file: String,
+    #[schema(example = "Tests.Login.Valid Login")]
+    pub test: String,
+}
+
+/// Failures sharing a normalized message
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct FailureCluster {
+    #[schema(example = "Element 'id=login' not visible after <N> seconds.")]
+    pub pattern: String,
+    pub count: usize,
+    /// A few of the distinct original messages.
+    pub samples: Vec<String>,
+    pub tests: Vec<FailedTest>,
+}
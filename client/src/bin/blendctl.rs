@@ -0,0 +1,565 @@
+//! Command line client for the blend api
+//!
+//! Exits with 0 on success, 1 when the blended or compared results have failing
+//! tests and 2 on any other error.
+use axum_test_client::analysis::{BlendSummary, Comparison};
+use axum_test_client::blend::{BlendFile, BlendRequest, CompareInput, CompareRequest};
+use axum_test_client::filter::Filter;
+use axum_test_client::{Client, ClientBuilder};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::ExitCode;
+
+const DEFAULT_URL: &str = "https://localhost:44001";
+
+/// Exit code when tests failed
+const TESTS_FAILED: u8 = 1;
+/// Exit code for every other error
+const ERROR: u8 = 2;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Upload Robot Framework results, blend and compare them
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Server url, defaults to the config file or https://localhost:44001.
+    #[arg(long, env = "BLEND_URL")]
+    url: Option<String>,
+    /// Api key sent as `theapikey`.
+    #[arg(long, env = "BLEND_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+    /// PEM certificate to trust, e.g. the `cert.pem` of the server.
+    #[arg(long, env = "BLEND_CA_CERT")]
+    ca_cert: Option<PathBuf>,
+    /// Accept any server certificate.
+    #[arg(long)]
+    insecure: bool,
+    /// Config file, defaults to `blendctl.toml` or `~/.config/blendctl/config.toml`.
+    #[arg(long, env = "BLENDCTL_CONFIG")]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Upload files, glob patterns are expanded. Files are named by their path
+    /// below the directory all of them are in.
+    Upload {
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// List the uploaded files.
+    List,
+    /// Remove all uploaded files.
+    Clear,
+    /// Blend the uploaded files and save the result. Fails when the last file has failing tests.
+    Blend {
+        #[arg(long, value_enum, default_value_t = OutputFormat::Ods)]
+        format: OutputFormat,
+        /// File to write, `-` for stdout. Defaults to `blend.<format>`.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Remove the uploaded files after blending.
+        #[arg(long)]
+        consume: bool,
+        /// Depth handed to the blender.
+        #[arg(long)]
+        depth: Option<usize>,
+        #[command(flatten)]
+        selection: Selection,
+    },
+    /// Print test counts and differing tests. Fails when the last file has failing tests.
+    Summary {
+        #[command(flatten)]
+        selection: Selection,
+    },
+    /// Compare two runs. Fails when tests fail in the current run that did not in the baseline.
+    Diff {
+        /// Local result file or name of an uploaded file.
+        baseline: String,
+        /// Local result file or name of an uploaded file.
+        current: String,
+        /// Seconds a test has to get slower to count as a duration regression.
+        #[arg(long)]
+        threshold: Option<f64>,
+        /// Also write the html report to this file.
+        #[arg(long)]
+        html: Option<PathBuf>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum OutputFormat {
+    /// Blended spreadsheet.
+    Ods,
+    /// Html report of the uploaded files.
+    Html,
+    /// Uploaded files merged into one output.xml.
+    Xml,
+    /// Blend summary.
+    Json,
+}
+
+impl OutputFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Ods => "ods",
+            OutputFormat::Html => "html",
+            OutputFormat::Xml => "xml",
+            OutputFormat::Json => "json",
+        }
+    }
+}
+
+/// Files and tests to blend
+#[derive(Args)]
+struct Selection {
+    /// Uploaded files in column order, as `name` or `name=title`. All files when not given.
+    /// Not allowed for the html and xml output, they always cover all files.
+    #[arg(long = "file")]
+    files: Vec<String>,
+    #[arg(long)]
+    include_suite: Vec<String>,
+    #[arg(long)]
+    exclude_suite: Vec<String>,
+    #[arg(long)]
+    include_test: Vec<String>,
+    #[arg(long)]
+    exclude_test: Vec<String>,
+    #[arg(long)]
+    include_tag: Vec<String>,
+    #[arg(long)]
+    exclude_tag: Vec<String>,
+}
+
+impl Selection {
+    fn filter(&self) -> Filter {
+        Filter {
+            include_suites: self.include_suite.clone(),
+            exclude_suites: self.exclude_suite.clone(),
+            include_tests: self.include_test.clone(),
+            exclude_tests: self.exclude_test.clone(),
+            include_tags: self.include_tag.clone(),
+            exclude_tags: self.exclude_tag.clone(),
+        }
+    }
+
+    fn request(&self, depth: Option<usize>) -> BlendRequest {
+        let files = self
+            .files
+            .iter()
+            .map(|file| match file.split_once('=') {
+                Some((name, title)) => BlendFile {
+                    name: name.to_string(),
+                    display_name: Some(title.to_string()),
+                },
+                None => BlendFile {
+                    name: file.clone(),
+                    display_name: None,
+                },
+            })
+            .collect();
+        let default = BlendRequest::default();
+        BlendRequest {
+            files,
+            depth: depth.unwrap_or(default.depth),
+            filter: self.filter(),
+            ..default
+        }
+    }
+}
+
+/// Settings read from the config file
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    url: Option<String>,
+    api_key: Option<String>,
+    ca_cert: Option<PathBuf>,
+    #[serde(default)]
+    insecure: bool,
+}
+
+impl Config {
+    /// The given file, else the first default location that exists.
+    fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_config_paths()
+                .into_iter()
+                .find(|path| path.is_file())
+            {
+                Some(path) => path,
+                None => return Ok(Config::default()),
+            },
+        };
+        let text = fs::read_to_string(&path)
+            .map_err(|error| format!("cannot read {}: {error}", path.display()))?;
+        Ok(toml::from_str(&text).map_err(|error| format!("{}: {error}", path.display()))?)
+    }
+}
+
+fn default_config_paths() -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from("blendctl.toml")];
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+    if let Some(config_home) = config_home {
+        paths.push(config_home.join("blendctl").join("config.toml"));
+    }
+    paths
+}
+
+/// Options override the environment, which overrides the config file.
+fn client(cli: &Cli) -> Result<Client> {
+    let config = Config::load(cli.config.as_deref())?;
+    let url = cli
+        .url
+        .clone()
+        .or(config.url)
+        .unwrap_or_else(|| String::from(DEFAULT_URL));
+    let mut builder =
+        ClientBuilder::new(url).danger_accept_invalid_certs(cli.insecure || config.insecure);
+    if let Some(api_key) = cli.api_key.clone().or(config.api_key) {
+        builder = builder.api_key(api_key);
+    }
+    if let Some(ca_cert) = cli.ca_cert.clone().or(config.ca_cert) {
+        let pem = fs::read(&ca_cert)
+            .map_err(|error| format!("cannot read {}: {error}", ca_cert.display()))?;
+        builder = builder.add_root_certificate_pem(&pem)?;
+    }
+    Ok(builder.build()?)
+}
+
+/// Paths of the files and glob patterns, a pattern matching nothing is an error.
+fn expand(patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        let matches = glob::glob(pattern)?.collect::<std::result::Result<Vec<_>, _>>()?;
+        if matches.is_empty() {
+            return Err(format!("no files match {pattern}").into());
+        }
+        paths.extend(matches);
+    }
+    Ok(paths)
+}
+
+/// Upload names of `paths`, relative to their common directory so that
+/// `results/*/output.xml` gives `a/output.xml`, `b/output.xml` and so on.
+/// Files outside of that directory, such as `../output.xml` next to
+/// `output.xml`, cannot be named.
+fn upload_names(paths: &[PathBuf]) -> Result<Vec<String>> {
+    let mut root = paths
+        .first()
+        .and_then(|path| path.parent())
+        .unwrap_or(Path::new(""));
+    for path in paths {
+        while !path.starts_with(root) {
+            root = root.parent().unwrap_or(Path::new(""));
+        }
+    }
+    let mut names: Vec<String> = Vec::new();
+    for path in paths {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        let mut parts = Vec::new();
+        for component in relative.components() {
+            match component {
+                Component::Normal(part) => parts.push(part.to_string_lossy()),
+                Component::ParentDir => {
+                    return Err(
+                        format!("{} is outside the common directory", path.display()).into(),
+                    )
+                }
+                Component::Prefix(_) | Component::RootDir | Component::CurDir => (),
+            }
+        }
+        let name = parts.join("/");
+        if name.is_empty() {
+            return Err(format!("not a file: {}", path.display()).into());
+        }
+        if names.contains(&name) {
+            return Err(format!("{} is given more than once", path.display()).into());
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+fn write_output(path: &Path, data: &[u8]) -> Result<()> {
+    if path == Path::new("-") {
+        io::stdout().write_all(data)?;
+    } else {
+        fs::write(path, data)
+            .map_err(|error| format!("cannot write {}: {error}", path.display()))?;
+        eprintln!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+fn print_summary(summary: &BlendSummary) {
+    println!(
+        "{:<40} {:>6} {:>6} {:>6} {:>7}",
+        "File", "Total", "Pass", "Fail", "Skip"
+    );
+    for file in &summary.files {
+        println!(
+            "{:<40} {:>6} {:>6} {:>6} {:>7}",
+            file.name, file.total, file.passed, file.failed, file.skipped
+        );
+    }
+    if !summary.differing.is_empty() {
+        println!("\nDiffering tests:");
+        for test in &summary.differing {
+            let statuses: Vec<&str> = test
+                .statuses
+                .iter()
+                .map(|status| status.map_or("-", |status| status.as_str()))
+                .collect();
+            println!("  {} [{}]", test.test, statuses.join(", "));
+        }
+    }
+    if !summary.newly_failing.is_empty() {
+        println!("\nNewly failing:");
+        for test in &summary.newly_failing {
+            println!("  {test}");
+        }
+    }
+}
+
+fn print_comparison(comparison: &Comparison) {
+    let sections = [
+        ("Newly failing", &comparison.newly_failing),
+        ("Newly passing", &comparison.newly_passing),
+        ("Added", &comparison.added),
+        ("Removed", &comparison.removed),
+    ];
+    for (title, tests) in sections {
+        if !tests.is_empty() {
+            println!("{title}:");
+            for test in tests {
+                println!("  {test}");
+            }
+        }
+    }
+    if !comparison.changed_messages.is_empty() {
+        println!("Changed messages:");
+        for change in &comparison.changed_messages {
+            println!(
+                "  {}\n    - {}\n    + {}",
+                change.test, change.baseline, change.current
+            );
+        }
+    }
+    if !comparison.duration_regressions.is_empty() {
+        println!("Slower:");
+        for change in &comparison.duration_regressions {
+            println!(
+                "  {} {:.3}s -> {:.3}s",
+                change.test, change.baseline, change.current
+            );
+        }
+    }
+}
+
+/// Tests failed in the last blended file.
+fn failed(summary: &BlendSummary) -> bool {
+    summary.files.last().is_some_and(|file| file.failed > 0)
+}
+
+/// A local file is sent as content, anything else is taken as an uploaded name.
+fn compare_input(run: &str) -> Result<CompareInput> {
+    if Path::new(run).is_file() {
+        Ok(CompareInput::Xml(fs::read_to_string(run)?))
+    } else {
+        Ok(CompareInput::Stored(run.to_string()))
+    }
+}
+
+async fn run(cli: Cli) -> Result<u8> {
+    let client = client(&cli)?;
+    let blend = client.blend();
+    match &cli.command {
+        Command::Upload { files } => {
+            let paths = expand(files)?;
+            for (path, name) in paths.iter().zip(upload_names(&paths)?) {
+                let data = fs::read_to_string(path)
+                    .map_err(|error| format!("cannot read {}: {error}", path.display()))?;
+                blend.upload(&name, data).await?;
+                eprintln!("Uploaded {name}");
+            }
+        }
+        Command::List => {
+            for name in blend.list().await? {
+                println!("{name}");
+            }
+        }
+        Command::Clear => blend.clear().await?,
+        Command::Blend {
+            format,
+            output,
+            consume,
+            depth,
+            selection,
+        } => {
+            let request = selection.request(*depth);
+            // before blending, a consuming blend leaves nothing to summarize
+            let summary = blend.summary(&request).await?;
+            let data = match format {
+                OutputFormat::Ods => blend.blend(&request, *consume).await?,
+                OutputFormat::Html => blend.report(&request.filter).await?.into_bytes(),
+                OutputFormat::Xml => blend.merge(&request.filter).await?.into_bytes(),
+                OutputFormat::Json => serde_json::to_vec_pretty(&summary)?,
+            };
+            let default_output = PathBuf::from(format!("blend.{}", format.extension()));
+            write_output(output.as_ref().unwrap_or(&default_output), &data)?;
+            if *consume && !matches!(format, OutputFormat::Ods) {
+                blend.clear().await?;
+            }
+            if failed(&summary) {
+                return Ok(TESTS_FAILED);
+            }
+        }
+        Command::Summary { selection } => {
+            let summary = blend.summary(&selection.request(None)).await?;
+            print_summary(&summary);
+            if failed(&summary) {
+                return Ok(TESTS_FAILED);
+            }
+        }
+        Command::Diff {
+            baseline,
+            current,
+            threshold,
+            html,
+        } => {
+            let request = CompareRequest {
+                baseline: compare_input(baseline)?,
+                current: compare_input(current)?,
+                duration_threshold: threshold
+                    .unwrap_or(axum_test_client::blend::DEFAULT_DURATION_THRESHOLD),
+            };
+            let comparison = blend.compare(&request).await?;
+            print_comparison(&comparison);
+            if let Some(html) = html {
+                write_output(html, blend.compare_report(&request).await?.as_bytes())?;
+            }
+            if !comparison.newly_failing.is_empty() {
+                return Ok(TESTS_FAILED);
+            }
+        }
+    }
+    Ok(0)
+}
+
+/// Option combinations clap cannot express.
+fn check_usage(cli: &Cli) -> std::result::Result<(), clap::Error> {
+    if let Command::Blend {
+        format: format @ (OutputFormat::Html | OutputFormat::Xml),
+        selection,
+        ..
+    } = &cli.command
+    {
+        if !selection.files.is_empty() {
+            return Err(Cli::command().error(
+                ErrorKind::ArgumentConflict,
+                format!(
+                    "--file cannot be used with --format {}, it always covers all files",
+                    format.extension()
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if let Err(error) = check_usage(&cli) {
+        error.exit();
+    }
+    match run(cli).await {
+        Ok(code) => ExitCode::from(code),
+        Err(error) => {
+            eprintln!("blendctl: {error}");
+            ExitCode::from(ERROR)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(paths: &[&str]) -> Result<Vec<String>> {
+        upload_names(&paths.iter().map(PathBuf::from).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn upload_names_below_common_directory() {
+        assert_eq!(names(&["results/output.xml"]).unwrap(), ["output.xml"]);
+        assert_eq!(
+            names(&["results/a/output.xml", "results/b/output.xml"]).unwrap(),
+            ["a/output.xml", "b/output.xml"]
+        );
+        assert_eq!(
+            names(&["results/output.xml", "results/rerun/1/output.xml"]).unwrap(),
+            ["output.xml", "rerun/1/output.xml"]
+        );
+        assert_eq!(
+            names(&["./a.xml", "b/c.xml"]).unwrap(),
+            ["a.xml", "b/c.xml"]
+        );
+    }
+
+    #[test]
+    fn upload_names_of_parent_directories() {
+        assert_eq!(
+            names(&["../results/a.xml", "../results/b.xml"]).unwrap(),
+            ["a.xml", "b.xml"]
+        );
+        assert_eq!(names(&["results/../a.xml"]).unwrap(), ["a.xml"]);
+        assert!(names(&["../a.xml", "b.xml"]).is_err());
+        assert!(names(&["a/b.xml", "a/../../c.xml"]).is_err());
+    }
+
+    #[test]
+    fn upload_names_of_absolute_paths() {
+        assert_eq!(
+            names(&["/tmp/results/a.xml", "/tmp/results/b/c.xml"]).unwrap(),
+            ["a.xml", "b/c.xml"]
+        );
+        assert_eq!(
+            names(&["/tmp/a.xml", "/srv/b.xml"]).unwrap(),
+            ["tmp/a.xml", "srv/b.xml"]
+        );
+    }
+
+    #[test]
+    fn upload_names_are_unique() {
+        assert!(names(&["a/output.xml", "a/output.xml"]).is_err());
+        assert!(names(&["a/output.xml", "a/./output.xml"]).is_err());
+        assert!(names(&["/"]).is_err());
+    }
+
+    #[test]
+    fn file_selection_with_html_and_xml_output() {
+        let usage = |args: &[&str]| {
+            check_usage(&Cli::try_parse_from([&["blendctl", "blend"], args].concat()).unwrap())
+        };
+        assert!(usage(&["--file", "a.xml"]).is_ok());
+        assert!(usage(&["--format", "json", "--file", "a.xml"]).is_ok());
+        assert!(usage(&["--format", "html"]).is_ok());
+        for format in ["html", "xml"] {
+            let error = usage(&["--format", format, "--file", "a.xml"]).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::ArgumentConflict);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Requests and errors of the blend endpoints
+use crate::filter::Filter;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Blend operation errors
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub enum BlendError {
+    /// Already exists conflict.
+    #[schema(example = "Item already exists")]
+    Conflict(String),
+    /// Not found by id.
+    #[schema(example = "id = 1")]
+    NotFound(String),
+    /// Operation unauthorized
+    #[schema(example = "missing api key")]
+    Unauthorized(String),
+    /// No representation accepted by the `Accept` header.
+    #[schema(example = "accept: image/png")]
+    NotAcceptable(String),
+    /// Uploaded data could not be read.
+    #[schema(example = "output_a.xml: unexpected end of file")]
+    InvalidInput(String),
+    /// History database failure.
+    #[schema(example = "database is locked")]
+    Database(String),
+    /// Blend could not be written.
+    #[schema(example = "sheet could not be written")]
+    Export(String),
+}
+
+/// Representation returned by convert
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ConvertFormat {
+    /// Output of `blend_result::parse_from_str_to_str`, `text/plain`.
+    Text,
+    /// Typed tree of suites, tests and keywords, `application/json`.
+    Json,
+    /// One row per test, `text/csv`.
+    Csv,
+    /// JUnit XML, `application/xml`.
+    Junit,
+    /// Outcome counts and a table of all tests, `text/markdown`.
+    Markdown,
+}
+
+impl ConvertFormat {
+    pub fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type {
+            "text/plain" => Some(ConvertFormat::Text),
+            "application/json" => Some(ConvertFormat::Json),
+            "text/csv" => Some(ConvertFormat::Csv),
+            "application/xml" | "text/xml" | "application/junit+xml" => Some(ConvertFormat::Junit),
+            "text/markdown" => Some(ConvertFormat::Markdown),
+            _ => None,
+        }
+    }
+}
+
+/// A file selected for blending
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BlendFile {
+    /// Name the file was uploaded with.
+    #[schema(example = "output_a.xml")]
+    pub name: String,
+    /// Column title in the blended result, defaults to `name`.
+    #[schema(example = "Run A")]
+    pub display_name: Option<String>,
+}
+
+/// Labels to record a blend in the history with
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RecordLabels {
+    #[schema(example = "webshop")]
+    pub project: String,
+    #[schema(example = "main")]
+    pub branch: String,
+    /// Time of the runs as RFC 3339, defaults to the time of the blend. Stored in UTC.
+    #[schema(example = "2025-05-01T12:00:00Z")]
+    pub timestamp: Option<String>,
+}
+
+/// Blend parameters
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BlendRequest {
+    /// Files to blend in column order. All uploaded files are blended when empty.
+    #[serde(default)]
+    pub files: Vec<BlendFile>,
+    /// Depth handed to `blend_result::blend_results::blend`.
+    #[serde(default = "default_depth")]
+    #[schema(default = 5, example = 5)]
+    pub depth: usize,
+    /// Record the test outcomes of this blend in the history.
+    pub record: Option<RecordLabels>,
+    /// Tests to keep, applied before blending.
+    #[serde(default)]
+    pub filter: Filter,
+}
+
+pub const DEFAULT_BLEND_DEPTH: usize = 5;
+
+fn default_depth() -> usize {
+    DEFAULT_BLEND_DEPTH
+}
+
+impl Default for BlendRequest {
+    fn default() -> Self {
+        BlendRequest {
+            files: Vec::default(),
+            depth: DEFAULT_BLEND_DEPTH,
+            record: None,
+            filter: Filter::default(),
+        }
+    }
+}
+
+/// A run to compare
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CompareInput {
+    /// Name of an uploaded file.
+    #[schema(example = "output_a.xml")]
+    Stored(String),
+    /// Content of a result file, converted like an upload.
+    Xml(String),
+}
+
+/// Compare parameters
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CompareRequest {
+    pub baseline: CompareInput,
+    pub current: CompareInput,
+    /// Seconds a test has to get slower to count as a duration regression.
+    #[serde(default = "default_duration_threshold")]
+    #[schema(default = 1.0, example = 1.0)]
+    pub duration_threshold: f64,
+}
+
+pub const DEFAULT_DURATION_THRESHOLD: f64 = 1.0;
+
+fn default_duration_threshold() -> f64 {
+    DEFAULT_DURATION_THRESHOLD
+}
@@ -0,0 +1,477 @@
+//! Http client for the api
+use crate::analysis::{BlendSummary, Comparison, FailureCluster, FlakyTest, Timing};
+use crate::blend::{BlendRequest, CompareRequest, ConvertFormat};
+use crate::filter::Filter;
+use crate::history::{TestOutcome, TrendPoint};
+use crate::robot::RobotResult;
+use crate::todo::{NewTodo, Todo, TodoListQuery, TodoPatch, TodoSearch, TodoUpdate, MAX_TODO_PAGE};
+use reqwest::{header, Certificate, Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::time::Duration;
+use url::Url;
+
+/// Header the server reads the api key from
+const API_KEY_HEADER: &str = "theapikey";
+
+/// Client errors
+#[derive(Debug)]
+pub enum Error {
+    /// The base url is not an http url.
+    BaseUrl(String),
+    /// Connection, TLS or decoding failure.
+    Http(reqwest::Error),
+    /// The server answered with an error status, `body` usually holds the json error.
+    Status { status: StatusCode, body: String },
+    /// The `next` link of a page is no url on the server.
+    Link(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::BaseUrl(error) => write!(f, "invalid base url: {error}"),
+            Error::Http(error) => write!(f, "request failed: {error}"),
+            Error::Status { status, body } => write!(f, "server answered {status}: {body}"),
+            Error::Link(link) => write!(f, "invalid next link: {link}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        Error::Http(error)
+    }
+}
+
+/// Builder for a [`Client`]
+pub struct ClientBuilder {
+    base_url: String,
+    api_key: Option<String>,
+    root_certificates: Vec<Certificate>,
+    accept_invalid_certs: bool,
+    timeout: Option<Duration>,
+}
+
+impl ClientBuilder {
+    /// Client for the server at `base_url`, e.g. `https://localhost:44001`.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        ClientBuilder {
+            base_url: base_url.into(),
+            api_key: None,
+            root_certificates: Vec::new(),
+            accept_invalid_certs: false,
+            timeout: None,
+        }
+    }
+
+    /// Key sent with every request. The blend api checks it against `API_KEY` of
+    /// the server, the todo and stuff api against a fixed key.
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Trust a PEM certificate, e.g. the self-signed `cert.pem` of the server.
+    pub fn add_root_certificate_pem(mut self, pem: &[u8]) -> Result<Self, Error> {
+        self.root_certificates.push(Certificate::from_pem(pem)?);
+        Ok(self)
+    }
+
+    /// Skip certificate validation. Only meant for local test servers.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Timeout of a whole request, none by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn build(self) -> Result<Client, Error> {
+        let base_url =
+            Url::parse(&self.base_url).map_err(|error| Error::BaseUrl(error.to_string()))?;
+        if base_url.cannot_be_a_base() {
+            return Err(Error::BaseUrl(format!("{base_url} cannot be a base")));
+        }
+        let mut http = reqwest::Client::builder()
+            .use_rustls_tls()
+            .danger_accept_invalid_certs(self.accept_invalid_certs);
+        for certificate in self.root_certificates {
+            http = http.add_root_certificate(certificate);
+        }
+        if let Some(timeout) = self.timeout {
+            http = http.timeout(timeout);
+        }
+        Ok(Client {
+            http: http.build()?,
+            base_url,
+            api_key: self.api_key,
+        })
+    }
+}
+
+/// Client for the whole api, cheap to clone
+#[derive(Clone, Debug)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: Url,
+    api_key: Option<String>,
+}
+
+impl Client {
+    /// Client with the default settings, see [`ClientBuilder`] for TLS options and api keys.
+    pub fn new(base_url: impl Into<String>) -> Result<Self, Error> {
+        ClientBuilder::new(base_url).build()
+    }
+
+    pub fn builder(base_url: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(base_url)
+    }
+
+    /// Endpoints below `/api/v1/blend`.
+    pub fn blend(&self) -> BlendClient<'_> {
+        BlendClient { client: self }
+    }
+
+    /// Endpoints below `/api/v1/todo`.
+    pub fn todo(&self) -> TodoClient<'_> {
+        TodoClient { client: self }
+    }
+
+    /// Endpoints below `/api/v1/stuff`.
+    pub fn stuff(&self) -> StuffClient<'_> {
+        StuffClient { client: self }
+    }
+
+    /// Request to the base url extended by the path segments, which are percent-encoded.
+    fn request(&self, method: Method, segments: &[&str]) -> RequestBuilder {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .expect("checked by the builder")
+            .pop_if_empty()
+            .extend(segments);
+        self.request_url(method, url)
+    }
+
+    /// Request to `url` with the api key set.
+    fn request_url(&self, method: Method, url: Url) -> RequestBuilder {
+        let request = self.http.request(method, url);
+        match &self.api_key {
+            Some(api_key) => request.header(API_KEY_HEADER, api_key),
+            None => request,
+        }
+    }
+}
+
+/// Send a request, turning error statuses into [`Error::Status`].
+async fn send(request: RequestBuilder) -> Result<Response, Error> {
+    let response = request.send().await?;
+    let status = response.status();
+    if status.is_success() {
+        Ok(response)
+    } else {
+        let body = response.text().await.unwrap_or_default();
+        Err(Error::Status { status, body })
+    }
+}
+
+async fn json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T, Error> {
+    Ok(send(request).await?.json().await?)
+}
+
+async fn text(request: RequestBuilder) -> Result<String, Error> {
+    Ok(send(request).await?.text().await?)
+}
+
+/// Target of the `rel="next"` link in the `Link` header of `response`.
+fn next_link(response: &Response) -> Option<String> {
+    let links = response.headers().get(header::LINK)?.to_str().ok()?;
+    links.split(',').find_map(|link| {
+        let (target, params) = link.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim().replace('"', "") == "rel=next")
+            .then(|| {
+                target
+                    .trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+    })
+}
+
+/// Filter patterns as the comma separated query the GET endpoints take.
+fn filter_query(filter: &Filter) -> Vec<(&'static str, String)> {
+    [
+        ("include_suites", &filter.include_suites),
+        ("exclude_suites", &filter.exclude_suites),
+        ("include_tests", &filter.include_tests),
+        ("exclude_tests", &filter.exclude_tests),
+        ("include_tags", &filter.include_tags),
+        ("exclude_tags", &filter.exclude_tags),
+    ]
+    .into_iter()
+    .filter(|(_, patterns)| !patterns.is_empty())
+    .map(|(name, patterns)| (name, patterns.join(",")))
+    .collect()
+}
+
+/// Blend endpoints
+pub struct BlendClient<'a> {
+    client: &'a Client,
+}
+
+impl BlendClient<'_> {
+    fn request(&self, method: Method, segments: &[&str]) -> RequestBuilder {
+        let path = [&["api", "v1", "blend"], segments].concat();
+        self.client.request(method, &path)
+    }
+
+    /// Convert a result file without storing it.
+    pub async fn convert(
+        &self,
+        data: impl Into<String>,
+        format: ConvertFormat,
+        filter: &Filter,
+    ) -> Result<String, Error> {
+        let request = self
+            .request(Method::POST, &["xml"])
+            .query(&[("format", format)])
+            .query(&filter_query(filter))
+            .body(data.into());
+        text(request).await
+    }
+
+    /// Read a result file into the result model without storing it.
+    pub async fn parse(
+        &self,
+        data: impl Into<String>,
+        filter: &Filter,
+    ) -> Result<RobotResult, Error> {
+        let request = self
+            .request(Method::POST, &["xml"])
+            .query(&[("format", ConvertFormat::Json)])
+            .query(&filter_query(filter))
+            .body(data.into());
+        json(request).await
+    }
+
+    /// Store a file to blend under `name`, which must not be uploaded already.
+    pub async fn upload(&self, name: &str, data: impl Into<String>) -> Result<(), Error> {
+        send(
+            self.request(Method::POST, &["upload", name])
+                .body(data.into()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Names of the uploaded files.
+    pub async fn list(&self) -> Result<Vec<String>, Error> {
+        json(self.request(Method::GET, &["list"])).await
+    }
+
+    /// Remove all uploaded files.
+    pub async fn clear(&self) -> Result<(), Error> {
+        send(self.request(Method::DELETE, &["list"])).await?;
+        Ok(())
+    }
+
+    /// Blended spreadsheet, the uploaded files are removed afterwards if `consume` is set.
+    pub async fn blend(&self, request: &BlendRequest, consume: bool) -> Result<Vec<u8>, Error> {
+        let request = self
+            .request(Method::POST, &["blend"])
+            .query(&[("consume", consume)])
+            .json(request);
+        Ok(send(request).await?.bytes().await?.to_vec())
+    }
+
+    pub async fn summary(&self, request: &BlendRequest) -> Result<BlendSummary, Error> {
+        json(self.request(Method::POST, &["summary"]).json(request)).await
+    }
+
+    pub async fn flaky(&self) -> Result<Vec<FlakyTest>, Error> {
+        json(self.request(Method::GET, &["flaky"])).await
+    }
+
+    /// Keyword timing with the `top` slowest tests and keywords per file.
+    pub async fn timing(&self, top: usize, filter: &Filter) -> Result<Timing, Error> {
+        let request = self
+            .request(Method::GET, &["timing"])
+            .query(&[("top", top)])
+            .query(&filter_query(filter));
+        json(request).await
+    }
+
+    pub async fn clusters(&self, filter: &Filter) -> Result<Vec<FailureCluster>, Error> {
+        json(
+            self.request(Method::GET, &["clusters"])
+                .query(&filter_query(filter)),
+        )
+        .await
+    }
+
+    /// Uploaded files merged into one output.xml.
+    pub async fn merge(&self, filter: &Filter) -> Result<String, Error> {
+        text(
+            self.request(Method::GET, &["merge"])
+                .query(&filter_query(filter)),
+        )
+        .await
+    }
+
+    /// Html report of the uploaded files.
+    pub async fn report(&self, filter: &Filter) -> Result<String, Error> {
+        text(
+            self.request(Method::GET, &["report"])
+                .query(&filter_query(filter)),
+        )
+        .await
+    }
+
+    pub async fn compare(&self, request: &CompareRequest) -> Result<Comparison, Error> {
+        json(self.request(Method::POST, &["compare"]).json(request)).await
+    }
+
+    /// Html report of a comparison.
+    pub async fn compare_report(&self, request: &CompareRequest) -> Result<String, Error> {
+        text(
+            self.request(Method::POST, &["compare", "report"])
+                .json(request),
+        )
+        .await
+    }
+
+    /// Outcomes of a test over the recorded blends of a project.
+    pub async fn test_history(
+        &self,
+        project: &str,
+        test: &str,
+        branch: Option<&str>,
+    ) -> Result<Vec<TestOutcome>, Error> {
+        let request = self
+            .request(Method::GET, &["history", project, "tests", test])
+            .query(&[("branch", branch)]);
+        json(request).await
+    }
+
+    /// Outcome counts per recorded blend of a project.
+    pub async fn trends(
+        &self,
+        project: &str,
+        branch: Option<&str>,
+        test: Option<&str>,
+    ) -> Result<Vec<TrendPoint>, Error> {
+        let request = self
+            .request(Method::GET, &["history", project, "trends"])
+            .query(&[("branch", branch), ("test", test)]);
+        json(request).await
+    }
+}
+
+/// Todo endpoints
+pub struct TodoClient<'a> {
+    client: &'a Client,
+}
+
+impl TodoClient<'_> {
+    fn request(&self, method: Method, segments: &[&str]) -> RequestBuilder {
+        let path = [&["api", "v1", "todo"], segments].concat();
+        self.client.request(method, &path)
+    }
+
+    /// One page of todos ordered by id.
+    pub async fn list(&self, query: &TodoListQuery) -> Result<Vec<Todo>, Error> {
+        json(self.request(Method::GET, &[]).query(query)).await
+    }
+
+    /// All todos, fetched page by page following the `next` links.
+    pub async fn list_all(&self) -> Result<Vec<Todo>, Error> {
+        let query = TodoListQuery {
+            cursor: None,
+            limit: Some(MAX_TODO_PAGE),
+        };
+        let mut request = self.request(Method::GET, &[]).query(&query);
+        let mut todos = Vec::new();
+        loop {
+            let response = send(request).await?;
+            let next = next_link(&response);
+            todos.extend(response.json::<Vec<Todo>>().await?);
+            let Some(next) = next else {
+                return Ok(todos);
+            };
+            // the api key must not be sent anywhere else
+            let base_url = &self.client.base_url;
+            let url = match base_url.join(&next) {
+                Ok(url) if url.origin() == base_url.origin() => url,
+                _ => return Err(Error::Link(next)),
+            };
+            request = self.client.request_url(Method::GET, url);
+        }
+    }
+
+    /// Todos matching `search`.
+    pub async fn search(&self, search: &TodoSearch) -> Result<Vec<Todo>, Error> {
+        json(self.request(Method::GET, &["search"]).query(search)).await
+    }
+
+    /// Create a todo, the returned one carries the id the server assigned.
+    pub async fn create(&self, value: &str) -> Result<Todo, Error> {
+        let todo = NewTodo {
+            value: String::from(value),
+        };
+        json(self.request(Method::POST, &[]).json(&todo)).await
+    }
+
+    /// Replace value and done status.
+    pub async fn replace(&self, id: i32, update: &TodoUpdate) -> Result<Todo, Error> {
+        json(self.request(Method::PUT, &[&id.to_string()]).json(update)).await
+    }
+
+    /// Change the fields set in `patch`.
+    pub async fn update(&self, id: i32, patch: &TodoPatch) -> Result<Todo, Error> {
+        json(self.request(Method::PATCH, &[&id.to_string()]).json(patch)).await
+    }
+
+    pub async fn mark_done(&self, id: i32) -> Result<Todo, Error> {
+        let patch = TodoPatch {
+            done: Some(true),
+            ..TodoPatch::default()
+        };
+        self.update(id, &patch).await
+    }
+
+    pub async fn delete(&self, id: i32) -> Result<(), Error> {
+        send(self.request(Method::DELETE, &[&id.to_string()])).await?;
+        Ok(())
+    }
+}
+
+/// Stuff endpoints
+pub struct StuffClient<'a> {
+    client: &'a Client,
+}
+
+impl StuffClient<'_> {
+    /// `Stuff` repeated `mul` times.
+    pub async fn stuff(&self, mul: u32) -> Result<String, Error> {
+        let mul = mul.to_string();
+        let request = self
+            .client
+            .request(Method::GET, &["api", "v1", "stuff", "stuff", &mul]);
+        json(request).await
+    }
+}
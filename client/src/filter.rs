@@ -0,0 +1,267 @@
+//! Include and exclude patterns for suites, tests and tags
+//!
+//! Patterns work like the ones of `robot --include`: `*` and `?` are
+//! wildcards, case, spaces and underscores are ignored.
+use crate::robot::{RobotResult, Suite, Test};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Patterns selecting the tests to keep
+#[derive(Serialize, Deserialize, ToSchema, Clone, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Filter {
+    /// Keep tests in matching suites, matched against the name and long name.
+    #[serde(default)]
+    #[schema(example = json!(["Tests.Login*"]))]
+    pub include_suites: Vec<String>,
+    #[serde(default)]
+    pub exclude_suites: Vec<String>,
+    /// Keep matching tests, matched against the name and long name.
+    #[serde(default)]
+    pub include_tests: Vec<String>,
+    #[serde(default)]
+    pub exclude_tests: Vec<String>,
+    /// Keep tests with at least one matching tag.
+    #[serde(default)]
+    #[schema(example = json!(["smoke", "critical"]))]
+    pub include_tags: Vec<String>,
+    #[serde(default)]
+    pub exclude_tags: Vec<String>,
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        self.include_suites.is_empty()
+            && self.exclude_suites.is_empty()
+            && self.include_tests.is_empty()
+            && self.exclude_tests.is_empty()
+            && self.include_tags.is_empty()
+            && self.exclude_tags.is_empty()
+    }
+
+    /// Drop the tests not selected and the suites left empty.
+    pub fn apply(&self, result: RobotResult) -> RobotResult {
+        let name = result.suite.name.clone();
+        let suite = self
+            .filter_suite(result.suite, &mut Vec::new())
+            .unwrap_or_else(|| Suite {
+                name,
+                suites: Vec::new(),
+                tests: Vec::new(),
+                keywords: Vec::new(),
+                message: String::new(),
+                start: None,
+                elapsed: None,
+            });
+        RobotResult { suite }
+    }
+
+    /// `path` holds the names of the enclosing suites.
+    fn filter_suite(&self, mut suite: Suite, path: &mut Vec<String>) -> Option<Suite> {
+        path.push(suite.name.clone());
+        suite.suites = std::mem::take(&mut suite.suites)
+            .into_iter()
+            .filter_map(|child| self.filter_suite(child, path))
+            .collect();
+        suite.tests.retain(|test| self.keeps(path, test));
+        path.pop();
+        if suite.suites.is_empty() && suite.tests.is_empty() {
+            None
+        } else {
+            Some(suite)
+        }
+    }
+
+    /// Whether `test` is selected, `suites` being the names of its enclosing suites from the
+    /// root. Suite patterns also select the tests of child suites.
+    pub fn keeps(&self, suites: &[impl AsRef<str>], test: &Test) -> bool {
+        let mut long_names: Vec<String> = Vec::with_capacity(suites.len());
+        for name in suites {
+            let long_name = match long_names.last() {
+                Some(parent) => format!("{parent}.{}", name.as_ref()),
+                None => name.as_ref().to_string(),
+            };
+            long_names.push(long_name);
+        }
+        let long_name = match long_names.last() {
+            Some(suite) => format!("{suite}.{}", test.name),
+            None => test.name.clone(),
+        };
+        let suite_matches = |patterns: &[String]| {
+            suites.iter().zip(&long_names).any(|(name, long_name)| {
+                any_match(patterns, name.as_ref()) || any_match(patterns, long_name)
+            })
+        };
+        let test_matches = |patterns: &[String]| {
+            any_match(patterns, &test.name) || any_match(patterns, &long_name)
+        };
+        let tag_matches =
+            |patterns: &[String]| test.tags.iter().any(|tag| any_match(patterns, tag));
+        (self.include_suites.is_empty() || suite_matches(&self.include_suites))
+            && (self.include_tests.is_empty() || test_matches(&self.include_tests))
+            && (self.include_tags.is_empty() || tag_matches(&self.include_tags))
+            && !suite_matches(&self.exclude_suites)
+            && !test_matches(&self.exclude_tests)
+            && !tag_matches(&self.exclude_tags)
+    }
+}
+
+fn any_match(patterns: &[String], text: &str) -> bool {
+    patterns.iter().any(|pattern| matches(pattern, text))
+}
+
+/// Lowercase without spaces and underscores, the way Robot Framework compares names.
+fn normalize(text: &str) -> Vec<char> {
+    text.chars()
+        .filter(|c| *c != ' ' && *c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Glob match with `*` for any text and `?` for a single character.
+fn matches(pattern: &str, text: &str) -> bool {
+    let pattern = normalize(pattern);
+    let text = normalize(text);
+    let (mut p, mut t) = (0, 0);
+    // position of the last `*` and the text position it currently covers up to
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robot::Status;
+
+    fn test(name: &str, tags: &[&str]) -> Test {
+        Test {
+            name: String::from(name),
+            status: Status::Pass,
+            message: String::new(),
+            start: None,
+            elapsed: None,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            keywords: Vec::new(),
+        }
+    }
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|pattern| pattern.to_string()).collect()
+    }
+
+    const SUITES: [&str; 2] = ["Tests", "Login"];
+
+    #[test]
+    fn glob() {
+        assert!(matches("Valid Login", "valid login"));
+        assert!(matches("valid_login", "Valid Login"));
+        assert!(matches("*Login", "Invalid Login"));
+        assert!(matches("Tests.*.Add*", "Tests.Cart.Add Item"));
+        assert!(matches("Log?n", "Login"));
+        assert!(matches("*", ""));
+        assert!(matches("a*b*c", "aXbYbZc"));
+        assert!(!matches("Log?n", "Logn"));
+        assert!(!matches("Log?n", "Logiin"));
+        assert!(!matches("Login", "Login Page"));
+        assert!(!matches("*Login", "Login Page"));
+        assert!(!matches("a*b*c", "aXbYcZ"));
+    }
+
+    #[test]
+    fn empty_filter_keeps_everything() {
+        assert!(Filter::default().keeps(&SUITES, &test("Valid Login", &[])));
+        assert!(Filter::default().keeps(&[] as &[&str], &test("Valid Login", &[])));
+    }
+
+    #[test]
+    fn suite_patterns() {
+        let include = |list: &[&str]| Filter {
+            include_suites: patterns(list),
+            ..Filter::default()
+        };
+        let valid_login = test("Valid Login", &[]);
+        assert!(include(&["Login"]).keeps(&SUITES, &valid_login));
+        assert!(include(&["Tests.Log*"]).keeps(&SUITES, &valid_login));
+        // parents select the tests of their children
+        assert!(include(&["Tests"]).keeps(&SUITES, &valid_login));
+        assert!(!include(&["Cart"]).keeps(&SUITES, &valid_login));
+        assert!(!include(&["Login.Tests"]).keeps(&SUITES, &valid_login));
+
+        let exclude = |list: &[&str]| Filter {
+            exclude_suites: patterns(list),
+            ..Filter::default()
+        };
+        assert!(!exclude(&["login"]).keeps(&SUITES, &valid_login));
+        assert!(!exclude(&["Tests"]).keeps(&SUITES, &valid_login));
+        assert!(exclude(&["Cart", "Tests.Cart"]).keeps(&SUITES, &valid_login));
+    }
+
+    #[test]
+    fn test_patterns() {
+        let include = |list: &[&str]| Filter {
+            include_tests: patterns(list),
+            ..Filter::default()
+        };
+        let valid_login = test("Valid Login", &[]);
+        assert!(include(&["Valid Login"]).keeps(&SUITES, &valid_login));
+        assert!(include(&["Tests.Login.Valid*"]).keeps(&SUITES, &valid_login));
+        assert!(include(&["Invalid*", "?alid Login"]).keeps(&SUITES, &valid_login));
+        assert!(!include(&["Invalid Login"]).keeps(&SUITES, &valid_login));
+        assert!(!include(&["Login.Valid Login"]).keeps(&SUITES, &valid_login));
+
+        let exclude = |list: &[&str]| Filter {
+            exclude_tests: patterns(list),
+            ..Filter::default()
+        };
+        assert!(!exclude(&["valid login"]).keeps(&SUITES, &valid_login));
+        assert!(!exclude(&["Tests.Login.*"]).keeps(&SUITES, &valid_login));
+        assert!(exclude(&["Invalid Login"]).keeps(&SUITES, &valid_login));
+    }
+
+    #[test]
+    fn tag_patterns() {
+        let smoke = test("Valid Login", &["smoke", "Owner-Alice"]);
+        let untagged = test("Invalid Login", &[]);
+        let include = Filter {
+            include_tags: patterns(&["SMOKE"]),
+            ..Filter::default()
+        };
+        assert!(include.keeps(&SUITES, &smoke));
+        assert!(!include.keeps(&SUITES, &untagged));
+
+        let exclude = Filter {
+            exclude_tags: patterns(&["owner-*"]),
+            ..Filter::default()
+        };
+        assert!(!exclude.keeps(&SUITES, &smoke));
+        assert!(exclude.keeps(&SUITES, &untagged));
+    }
+
+    #[test]
+    fn excludes_win_over_includes() {
+        let filter = Filter {
+            include_suites: patterns(&["Tests"]),
+            include_tags: patterns(&["smoke"]),
+            exclude_tests: patterns(&["Valid*"]),
+            ..Filter::default()
+        };
+        assert!(!filter.keeps(&SUITES, &test("Valid Login", &["smoke"])));
+        assert!(filter.keeps(&SUITES, &test("Logout", &["smoke"])));
+        assert!(!filter.keeps(&SUITES, &test("Logout", &[])));
+    }
+}
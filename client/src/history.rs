@@ -0,0 +1,34 @@
+//! Responses of the history endpoints
+use crate::robot::Status;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Outcome of a test in one run of a recorded blend
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct TestOutcome {
+    pub blend: i64,
+    #[schema(example = "2025-05-01T12:00:00Z")]
+    pub timestamp: String,
+    #[schema(example = "main")]
+    pub branch: String,
+    #[schema(example = "output_b.xml")]
+    pub run: String,
+    pub status: Status,
+    pub message: String,
+}
+
+/// Outcome counts of one recorded blend
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct TrendPoint {
+    pub blend: i64,
+    #[schema(example = "2025-05-01T12:00:00Z")]
+    pub timestamp: String,
+    #[schema(example = "main")]
+    pub branch: String,
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    /// Share of passing outcomes over passed and failed ones, between 0 and 1.
+    #[schema(example = 0.95)]
+    pub pass_rate: Option<f64>,
+}
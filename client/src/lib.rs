@@ -0,0 +1,30 @@
+//! Typed async client for the blend, todo and stuff api of axum-test-server
+//!
+//! The request and response types are the ones the server uses, so they can
+//! be used without the http client by turning off the default `client` feature.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), axum_test_client::Error> {
+//! use axum_test_client::ClientBuilder;
+//!
+//! let client = ClientBuilder::new("https://localhost:44001")
+//!     .api_key("rocks")
+//!     .add_root_certificate_pem(&std::fs::read("cert.pem").unwrap())?
+//!     .build()?;
+//! client.blend().upload("output_a.xml", "<robot/>").await?;
+//! println!("{:?}", client.blend().list().await?);
+//! # Ok(())
+//! # }
+//! ```
+pub mod analysis;
+pub mod blend;
+#[cfg(feature = "client")]
+mod client;
+pub mod filter;
+pub mod history;
+pub mod robot;
+pub mod stuff;
+pub mod todo;
+
+#[cfg(feature = "client")]
+pub use client::{BlendClient, Client, ClientBuilder, Error, StuffClient, TodoClient};
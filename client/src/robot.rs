@@ -0,0 +1,148 @@
+//! Minimal Robot Framework result model
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Test or keyword status
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Status {
+    Pass,
+    Fail,
+    Skip,
+    NotRun,
+}
+
+impl Status {
+    /// Status as written in output.xml.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Pass => "PASS",
+            Status::Fail => "FAIL",
+            Status::Skip => "SKIP",
+            Status::NotRun => "NOT RUN",
+        }
+    }
+}
+
+/// A status other than the ones Robot Framework writes
+#[derive(Debug)]
+pub struct UnknownStatus(pub String);
+
+impl fmt::Display for UnknownStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown status: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownStatus {}
+
+impl FromStr for Status {
+    type Err = UnknownStatus;
+
+    /// Status as written in output.xml or output.json.
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "PASS" => Ok(Status::Pass),
+            "FAIL" => Ok(Status::Fail),
+            "SKIP" => Ok(Status::Skip),
+            "NOT RUN" | "NOT_RUN" => Ok(Status::NotRun),
+            other => Err(UnknownStatus(other.to_string())),
+        }
+    }
+}
+
+/// A parsed result file
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct RobotResult {
+    pub suite: Suite,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct Suite {
+    #[schema(example = "Tests")]
+    pub name: String,
+    #[schema(no_recursion)]
+    pub suites: Vec<Suite>,
+    pub tests: Vec<Test>,
+    /// Suite setup and teardown.
+    pub keywords: Vec<Keyword>,
+    /// Message of the suite status, e.g. of a failed setup.
+    pub message: String,
+    /// Start as ISO 8601 local time, if known.
+    #[schema(example = "2024-05-01T12:00:00.000000")]
+    pub start: Option<String>,
+    /// Run time in seconds, if the status carries timestamps.
+    pub elapsed: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct Test {
+    #[schema(example = "Valid Login")]
+    pub name: String,
+    pub status: Status,
+    pub message: String,
+    /// Start as ISO 8601 local time, if known.
+    #[schema(example = "2024-05-01T12:00:01.015000")]
+    pub start: Option<String>,
+    /// Run time in seconds, if the status carries timestamps.
+    pub elapsed: Option<f64>,
+    #[schema(example = json!(["smoke", "critical"]))]
+    pub tags: Vec<String>,
+    pub keywords: Vec<Keyword>,
+}
+
+/// A keyword or control structure such as `FOR` or `IF`
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct Keyword {
+    /// Keyword name, or the condition of a control structure.
+    #[schema(example = "Input Text")]
+    pub name: String,
+    /// `KEYWORD`, `SETUP`, `TEARDOWN` or the kind of control structure.
+    #[serde(rename = "type")]
+    #[schema(example = "KEYWORD")]
+    pub kind: String,
+    #[schema(example = "SeleniumLibrary")]
+    pub library: Option<String>,
+    pub status: Status,
+    /// Start as ISO 8601 local time, if known.
+    #[schema(example = "2024-05-01T12:00:01.020000")]
+    pub start: Option<String>,
+    /// Run time in seconds, if the status carries timestamps.
+    pub elapsed: Option<f64>,
+    #[schema(no_recursion)]
+    pub keywords: Vec<Keyword>,
+}
+
+/// A test together with the names of its enclosing suites
+pub struct TestRef<'a> {
+    pub suite: String,
+    pub test: &'a Test,
+}
+
+impl TestRef<'_> {
+    /// Dotted long name as used by Robot Framework, e.g. `Tests.Login.Valid Login`.
+    pub fn long_name(&self) -> String {
+        format!("{}.{}", self.suite, self.test.name)
+    }
+}
+
+impl RobotResult {
+    /// All tests in document order.
+    pub fn tests(&self) -> Vec<TestRef<'_>> {
+        let mut tests = Vec::new();
+        collect_tests(&self.suite, &self.suite.name, &mut tests);
+        tests
+    }
+}
+
+fn collect_tests<'a>(suite: &'a Suite, long_name: &str, tests: &mut Vec<TestRef<'a>>) {
+    tests.extend(suite.tests.iter().map(|test| TestRef {
+        suite: long_name.to_string(),
+        test,
+    }));
+    for child in &suite.suites {
+        collect_tests(child, &format!("{long_name}.{}", child.name), tests);
+    }
+}
@@ -0,0 +1,17 @@
+//! Errors of the stuff endpoints
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Stuff operation errors
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub enum StuffError {
+    /// Already exists conflict.
+    #[schema(example = "Item already exists")]
+    Conflict(String),
+    /// Not found by id.
+    #[schema(example = "id = 1")]
+    NotFound(String),
+    /// Operation unauthorized
+    #[schema(example = "missing api key")]
+    Unauthorized(String),
+}
@@ -0,0 +1,138 @@
+//! Items and errors of the todo endpoints
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Item to do.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct Todo {
+    /// Assigned by the server, never reused.
+    pub id: i32,
+    #[schema(example = "Buy groceries")]
+    pub value: String,
+    pub done: bool,
+}
+
+/// Item to create, the server assigns the id
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct NewTodo {
+    #[schema(example = "Buy groceries")]
+    pub value: String,
+}
+
+/// Replacement of all fields of a todo
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TodoUpdate {
+    #[schema(example = "Buy groceries")]
+    pub value: String,
+    pub done: bool,
+}
+
+/// Fields of a todo to change, missing ones are kept
+#[derive(Serialize, Deserialize, ToSchema, Clone, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TodoPatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "Buy groceries")]
+    pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub done: Option<bool>,
+}
+
+impl From<TodoUpdate> for TodoPatch {
+    fn from(update: TodoUpdate) -> Self {
+        TodoPatch {
+            value: Some(update.value),
+            done: Some(update.done),
+        }
+    }
+}
+
+/// How the search value is compared with the todo values, always ignoring case
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// Whole value.
+    Exact,
+    /// Anywhere in the value.
+    #[default]
+    Substring,
+    /// Start of the value.
+    Prefix,
+    /// Regular expression in the syntax of the `regex` crate.
+    Regex,
+}
+
+/// Field todos are sorted by, ties are broken by id
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum TodoSort {
+    #[default]
+    Id,
+    Value,
+    Done,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Todo search query, every field is optional
+#[derive(Serialize, Deserialize, IntoParams, Clone, Default, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct TodoSearch {
+    /// Search by value, ignoring case. All todos match without it.
+    #[param(example = "groceries")]
+    pub value: Option<String>,
+    /// How `value` is matched, `substring` by default.
+    #[serde(rename = "match")]
+    #[param(inline)]
+    pub mode: Option<MatchMode>,
+    /// Search by `done` status.
+    pub done: Option<bool>,
+    /// Sort field, `id` by default.
+    #[param(inline)]
+    pub sort: Option<TodoSort>,
+    /// Sort order, `asc` by default.
+    #[param(inline)]
+    pub order: Option<SortOrder>,
+    /// Maximum number of todos returned, all by default.
+    pub limit: Option<usize>,
+    /// Number of matching todos skipped before the first one returned.
+    pub offset: Option<usize>,
+}
+
+pub const DEFAULT_TODO_PAGE: usize = 100;
+pub const MAX_TODO_PAGE: usize = 1000;
+
+/// Page of the todo list
+#[derive(Serialize, Deserialize, IntoParams, Clone, Default, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct TodoListQuery {
+    /// Id of the last todo of the previous page, the list starts at the first todo without it.
+    pub cursor: Option<i32>,
+    /// Number of todos per page, 100 by default and at most 1000.
+    pub limit: Option<usize>,
+}
+
+/// Todo operation errors
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub enum TodoError {
+    /// Todo not found by id.
+    #[schema(example = "id = 1")]
+    NotFound(String),
+    /// Todo operation unauthorized
+    #[schema(example = "missing api key")]
+    Unauthorized(String),
+    /// Request body is not a valid todo.
+    #[schema(example = "value must not be empty")]
+    InvalidInput(String),
+    /// Todo database failure.
+    #[schema(example = "database is locked")]
+    Database(String),
+}
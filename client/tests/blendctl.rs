@@ -0,0 +1,171 @@
+//! blendctl run against a stub server that answers every request the same way
+use axum_test_client::analysis::{BlendSummary, FileSummary};
+use axum_test_client::blend::BlendError;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Server answering every request with `status` and the json `body`. The heads
+/// of the requests it got come out of the receiver.
+fn stub_server(status: &'static str, body: String) -> (String, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut head = String::new();
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim_end().is_empty() {
+                    break;
+                }
+                if let Some(("content-length", value)) = line.to_lowercase().split_once(':') {
+                    length = value.trim().parse().unwrap();
+                }
+                head.push_str(&line);
+            }
+            reader.read_exact(&mut vec![0; length]).unwrap();
+            let _ = sender.send(head.to_lowercase());
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\ncontent-type: application/json\r\n\
+                 content-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        }
+    });
+    (url, receiver)
+}
+
+/// Url nothing listens on.
+fn closed_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    format!("http://{}", listener.local_addr().unwrap())
+}
+
+fn summary(failed: usize) -> String {
+    serde_json::to_string(&BlendSummary {
+        total_tests: 3,
+        files: vec![FileSummary {
+            name: String::from("output.xml"),
+            total: 3,
+            passed: 3 - failed,
+            failed,
+            skipped: 0,
+        }],
+        differing: Vec::new(),
+        newly_failing: Vec::new(),
+    })
+    .unwrap()
+}
+
+/// Config file named after the test.
+fn config(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("blendctl-{}-{name}.toml", std::process::id()));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+/// Exit code and stderr of blendctl run with only the given settings.
+fn blendctl(config: &PathBuf, envs: &[(&str, &str)], args: &[&str]) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_blendctl"))
+        .env_remove("BLEND_URL")
+        .env_remove("BLEND_API_KEY")
+        .env_remove("BLEND_CA_CERT")
+        .env("BLENDCTL_CONFIG", config)
+        .envs(envs.iter().copied())
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn exit_codes() {
+    let config = config("exit-codes", "");
+    let (passing, _) = stub_server("200 OK", summary(0));
+    let (failing, _) = stub_server("200 OK", summary(1));
+    let error = serde_json::to_string(&BlendError::Database(String::from("locked"))).unwrap();
+    let (broken, _) = stub_server("500 Internal Server Error", error);
+
+    assert_eq!(blendctl(&config, &[], &["--url", &passing, "summary"]).0, 0);
+    assert_eq!(blendctl(&config, &[], &["--url", &failing, "summary"]).0, 1);
+    let (code, stderr) = blendctl(&config, &[], &["--url", &broken, "summary"]);
+    assert_eq!(code, 2);
+    assert!(stderr.contains("locked"), "{stderr}");
+    assert_eq!(
+        blendctl(&config, &[], &["--url", &closed_url(), "list"]).0,
+        2
+    );
+
+    let (code, stderr) = blendctl(
+        &config,
+        &[],
+        &[
+            "--url", &passing, "blend", "--format", "html", "--file", "a.xml",
+        ],
+    );
+    assert_eq!(code, 2);
+    assert!(stderr.contains("--file cannot be used"), "{stderr}");
+    assert_eq!(blendctl(&config, &[], &["summary", "--depth", "1"]).0, 2);
+
+    let broken_config = self::config("broken-config", "url = 1");
+    assert_eq!(
+        blendctl(&broken_config, &[], &["--url", &passing, "summary"]).0,
+        2
+    );
+    std::fs::remove_file(config).unwrap();
+    std::fs::remove_file(broken_config).unwrap();
+}
+
+#[test]
+fn options_override_environment_and_config() {
+    let (url, requests) = stub_server("200 OK", summary(0));
+    let closed = closed_url();
+    let config = config(
+        "precedence",
+        &format!("url = \"{url}\"\napi_key = \"config-key\"\n"),
+    );
+    let api_key = |envs: &[(&str, &str)], args: &[&str]| {
+        let code = blendctl(&config, envs, &[args, &["summary"]].concat()).0;
+        assert_eq!(code, 0, "{envs:?} {args:?}");
+        let head = requests.try_recv().unwrap();
+        head.lines()
+            .find_map(|line| line.strip_prefix("theapikey: "))
+            .map(String::from)
+    };
+    assert_eq!(api_key(&[], &[]).as_deref(), Some("config-key"));
+    assert_eq!(
+        api_key(&[("BLEND_API_KEY", "env-key")], &[]).as_deref(),
+        Some("env-key")
+    );
+    assert_eq!(
+        api_key(
+            &[("BLEND_API_KEY", "env-key")],
+            &["--api-key", "option-key"]
+        )
+        .as_deref(),
+        Some("option-key")
+    );
+
+    let closed_config = self::config("precedence-closed", &format!("url = \"{closed}\""));
+    let code = |envs: &[(&str, &str)], args: &[&str]| {
+        blendctl(&closed_config, envs, &[args, &["summary"]].concat()).0
+    };
+    assert_eq!(code(&[], &[]), 2);
+    assert_eq!(code(&[("BLEND_URL", &url)], &[]), 0);
+    assert_eq!(code(&[("BLEND_URL", &closed)], &["--url", &url]), 0);
+    std::fs::remove_file(config).unwrap();
+    std::fs::remove_file(closed_config).unwrap();
+}
@@ -0,0 +1,401 @@
+use crate::robot::{Keyword, RobotResult, Status, Suite, Test};
+use axum_test_client::analysis::{
+    BlendSummary, Comparison, DurationChange, FailedTest, FailureCluster, FileSummary, FileTiming,
+    FlakyTest, KeywordTiming, MessageChange, RunFailure, Slowest, TestStatuses, Timing,
+};
+use regex::Regex;
+use spreadsheet_ods::Value;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// Long test names with their status per file, in order of first appearance.
+fn status_table(results: &[RobotResult]) -> Vec<(String, Vec<Option<Status>>)> {
+    let mut rows: Vec<(String, Vec<Option<Status>>)> = Vec::new();
+    let mut index = HashMap::new();
+    for (column, result) in results.iter().enumerate() {
+        for test in result.tests() {
+            let row = *index.entry(test.long_name()).or_insert_with_key(|name| {
+                rows.push((name.clone(), vec![None; results.len()]));
+                rows.len() - 1
+            });
+            rows[row].1[column] = Some(test.test.status);
+        }
+    }
+    rows
+}
+
+pub(crate) fn summarize(files: &[String], results: &[RobotResult]) -> BlendSummary {
+    let files_summary = files
+        .iter()
+        .zip(results)
+        .map(|(name, result)| {
+            let tests = result.tests();
+            let count = |status| tests.iter().filter(|t| t.test.status == status).count();
+            FileSummary {
+                name: name.clone(),
+                total: tests.len(),
+                passed: count(Status::Pass),
+                failed: count(Status::Fail),
+                skipped: count(Status::Skip),
+            }
+        })
+        .collect();
+
+    let table = status_table(results);
+    let newly_failing = match results.len() {
+        0 | 1 => Vec::new(),
+        len => table
+            .iter()
+            .filter(|(_, statuses)| {
+                statuses[len - 1] == Some(Status::Fail) && statuses[len - 2] != Some(Status::Fail)
+            })
+            .map(|(test, _)| test.clone())
+            .collect(),
+    };
+    BlendSummary {
+        total_tests: table.len(),
+        files: files_summary,
+        newly_failing,
+        differing: table
+            .into_iter()
+            .filter(|(_, statuses)| statuses.iter().any(|status| *status != statuses[0]))
+            .map(|(test, statuses)| TestStatuses { test, statuses })
+            .collect(),
+    }
+}
+
+/// Tests with mixed outcomes over the runs, the flakiest first.
+pub(crate) fn find_flaky(files: &[String], results: &[RobotResult]) -> Vec<FlakyTest> {
+    let mut tests: Vec<FlakyTest> = Vec::new();
+    let mut index = HashMap::new();
+    for (run, result) in files.iter().zip(results) {
+        for test in result.tests() {
+            let row = *index.entry(test.long_name()).or_insert_with_key(|name| {
+                tests.push(FlakyTest {
+                    test: name.clone(),
+                    runs: 0,
+                    passed: 0,
+                    failed: 0,
+                    flakiness: 0.0,
+                    failures: Vec::new(),
+                });
+                tests.len() - 1
+            });
+            let flaky = &mut tests[row];
+            match test.test.status {
+                Status::Pass => flaky.passed += 1,
+                Status::Fail => {
+                    flaky.failed += 1;
+                    flaky.failures.push(RunFailure {
+                        run: run.clone(),
+                        message: test.test.message.clone(),
+                    });
+                }
+                Status::Skip | Status::NotRun => continue,
+            }
+            flaky.runs += 1;
+        }
+    }
+    let mut flaky: Vec<FlakyTest> = tests
+        .into_iter()
+        .filter(|test| test.passed > 0 && test.failed > 0)
+        .map(|test| FlakyTest {
+            flakiness: test.failed as f64 / test.runs as f64,
+            ..test
+        })
+        .collect();
+    flaky.sort_by(|a, b| b.flakiness.total_cmp(&a.flakiness));
+    flaky
+}
+
+/// Rows of the flaky sheet in the ODS export, starting with a header.
+pub(crate) fn flaky_sheet(flaky: &[FlakyTest]) -> Vec<Vec<Value>> {
+    let mut rows = vec![[
+        "Test",
+        "Runs",
+        "Passed",
+        "Failed",
+        "Flakiness",
+        "Failed In",
+        "Messages",
+    ]
+    .into_iter()
+    .map(Value::from)
+    .collect()];
+    for test in flaky {
+        rows.push(vec![
+            Value::from(&test.test),
+            Value::from(test.runs as u32),
+            Value::from(test.passed as u32),
+            Value::from(test.failed as u32),
+            Value::from(test.flakiness),
+            Value::from(
+                test.failures
+                    .iter()
+                    .map(|failure| failure.run.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            Value::from(
+                test.failures
+                    .iter()
+                    .map(|failure| format!("{}: {}", failure.run, failure.message))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+        ]);
+    }
+    rows
+}
+
+/// Compare two runs, reporting run times growing by more than `threshold` seconds.
+pub(crate) fn compare(baseline: &RobotResult, current: &RobotResult, threshold: f64) -> Comparison {
+    let baseline_tests = baseline.tests();
+    let before: HashMap<String, &Test> = baseline_tests
+        .iter()
+        .map(|test| (test.long_name(), test.test))
+        .collect();
+    let current_tests = current.tests();
+    let after: HashMap<String, &Test> = current_tests
+        .iter()
+        .map(|test| (test.long_name(), test.test))
+        .collect();
+
+    let mut comparison = Comparison {
+        added: Vec::new(),
+        removed: baseline_tests
+            .iter()
+            .map(|test| test.long_name())
+            .filter(|name| !after.contains_key(name))
+            .collect(),
+        newly_failing: Vec::new(),
+        newly_passing: Vec::new(),
+        changed_messages: Vec::new(),
+        duration_regressions: Vec::new(),
+    };
+    for test in &current_tests {
+        let name = test.long_name();
+        let (old, new) = match before.get(&name) {
+            Some(old) => (*old, test.test),
+            None => {
+                comparison.added.push(name);
+                continue;
+            }
+        };
+        if new.status == Status::Fail && old.status != Status::Fail {
+            comparison.newly_failing.push(name.clone());
+        }
+        if new.status == Status::Pass && old.status != Status::Pass {
+            comparison.newly_passing.push(name.clone());
+        }
+        if new.message != old.message {
+            comparison.changed_messages.push(MessageChange {
+                test: name.clone(),
+                baseline: old.message.clone(),
+                current: new.message.clone(),
+            });
+        }
+        if let (Some(baseline), Some(current)) = (old.elapsed, new.elapsed) {
+            if current - baseline > threshold {
+                comparison.duration_regressions.push(DurationChange {
+                    test: name,
+                    baseline,
+                    current,
+                });
+            }
+        }
+    }
+    comparison
+        .duration_regressions
+        .sort_by(|a, b| (b.current - b.baseline).total_cmp(&(a.current - a.baseline)));
+    comparison
+}
+
+fn keyword_name(keyword: &Keyword) -> String {
+    match &keyword.library {
+        Some(library) if !library.is_empty() => format!("{library}.{}", keyword.name),
+        _ => keyword.name.clone(),
+    }
+}
+
+/// Timed keyword calls, control structures are descended into but not counted.
+fn collect_keyword_times(keywords: &[Keyword], times: &mut Vec<(String, f64)>) {
+    for keyword in keywords {
+        if matches!(keyword.kind.as_str(), "KEYWORD" | "SETUP" | "TEARDOWN") {
+            if let Some(elapsed) = keyword.elapsed {
+                times.push((keyword_name(keyword), elapsed));
+            }
+        }
+        collect_keyword_times(&keyword.keywords, times);
+    }
+}
+
+fn collect_suite_keyword_times(suite: &Suite, times: &mut Vec<(String, f64)>) {
+    collect_keyword_times(&suite.keywords, times);
+    for test in &suite.tests {
+        collect_keyword_times(&test.keywords, times);
+    }
+    for child in &suite.suites {
+        collect_suite_keyword_times(child, times);
+    }
+}
+
+/// Nearest-rank percentile of sorted values.
+fn percentile(sorted: &[f64], percent: f64) -> f64 {
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn slowest(mut items: Vec<(String, f64)>, top: usize) -> Vec<Slowest> {
+    items.sort_by(|a, b| b.1.total_cmp(&a.1));
+    items
+        .into_iter()
+        .take(top)
+        .map(|(name, elapsed)| Slowest { name, elapsed })
+        .collect()
+}
+
+/// Keyword statistics over all files and the `top` slowest tests and keyword calls per file.
+pub(crate) fn timing(files: &[String], results: &[RobotResult], top: usize) -> Timing {
+    let mut durations: Vec<(String, Vec<f64>)> = Vec::new();
+    let mut index = HashMap::new();
+    let mut file_timings = Vec::new();
+    for (file, result) in files.iter().zip(results) {
+        let mut times = Vec::new();
+        collect_suite_keyword_times(&result.suite, &mut times);
+        for (keyword, elapsed) in &times {
+            let row = *index.entry(keyword.clone()).or_insert_with(|| {
+                durations.push((keyword.clone(), Vec::new()));
+                durations.len() - 1
+            });
+            durations[row].1.push(*elapsed);
+        }
+        let tests = result
+            .tests()
+            .iter()
+            .filter_map(|test| Some((test.long_name(), test.test.elapsed?)))
+            .collect();
+        file_timings.push(FileTiming {
+            file: file.clone(),
+            tests: slowest(tests, top),
+            keywords: slowest(times, top),
+        });
+    }
+    let mut keywords: Vec<KeywordTiming> = durations
+        .into_iter()
+        .map(|(keyword, mut times)| {
+            times.sort_by(f64::total_cmp);
+            KeywordTiming {
+                keyword,
+                calls: times.len(),
+                min: times[0],
+                avg: times.iter().sum::<f64>() / times.len() as f64,
+                max: times[times.len() - 1],
+                p95: percentile(&times, 95.0),
+            }
+        })
+        .collect();
+    keywords.sort_by(|a, b| b.p95.total_cmp(&a.p95));
+    Timing {
+        keywords,
+        files: file_timings,
+    }
+}
+
+/// Rows of the keyword timing sheet in the ODS export, starting with a header.
+pub(crate) fn keyword_timing_sheet(timing: &Timing) -> Vec<Vec<Value>> {
+    let mut rows = vec![["Keyword", "Calls", "Min", "Avg", "Max", "P95"]
+        .into_iter()
+        .map(Value::from)
+        .collect()];
+    for keyword in &timing.keywords {
+        rows.push(vec![
+            Value::from(&keyword.keyword),
+            Value::from(keyword.calls as u32),
+            Value::from(keyword.min),
+            Value::from(keyword.avg),
+            Value::from(keyword.max),
+            Value::from(keyword.p95),
+        ]);
+    }
+    rows
+}
+
+/// Rows of the slowest tests and keywords sheet in the ODS export, starting with a header.
+pub(crate) fn slowest_sheet(timing: &Timing) -> Vec<Vec<Value>> {
+    let mut rows = vec![["File", "Kind", "Name", "Elapsed"]
+        .into_iter()
+        .map(Value::from)
+        .collect()];
+    for file in &timing.files {
+        let tests = file.tests.iter().map(|test| ("Test", test));
+        let keywords = file.keywords.iter().map(|keyword| ("Keyword", keyword));
+        for (kind, item) in tests.chain(keywords) {
+            rows.push(vec![
+                Value::from(&file.file),
+                Value::from(kind),
+                Value::from(&item.name),
+                Value::from(item.elapsed),
+            ]);
+        }
+    }
+    rows
+}
+
+static UUID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b").unwrap()
+});
+static PATH: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(^|[\s'"(\[=:])(?:[A-Za-z]:\\|\.{0,2}/|~/)[^\s'")\]]*"#).unwrap()
+});
+static HEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b0x[0-9a-fA-F]+\b").unwrap());
+static NUMBER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d+(?:\.\d+)?").unwrap());
+
+/// Failure message with UUIDs, paths and numbers masked and whitespace collapsed.
+pub(crate) fn normalize_message(message: &str) -> String {
+    let message = UUID.replace_all(message, "<UUID>");
+    let message = PATH.replace_all(&message, "${1}<PATH>");
+    let message = HEX.replace_all(&message, "<HEX>");
+    let message = NUMBER.replace_all(&message, "<N>");
+    message.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Sample messages kept per cluster.
+const CLUSTER_SAMPLES: usize = 3;
+
+/// Failing tests of all files grouped by normalized message, the largest cluster first.
+pub(crate) fn cluster_failures(files: &[String], results: &[RobotResult]) -> Vec<FailureCluster> {
+    let mut clusters: Vec<FailureCluster> = Vec::new();
+    let mut index = HashMap::new();
+    for (file, result) in files.iter().zip(results) {
+        for test in result.tests() {
+            if test.test.status != Status::Fail {
+                continue;
+            }
+            let pattern = normalize_message(&test.test.message);
+            let row = *index.entry(pattern).or_insert_with_key(|pattern| {
+                clusters.push(FailureCluster {
+                    pattern: pattern.clone(),
+                    count: 0,
+                    samples: Vec::new(),
+                    tests: Vec::new(),
+                });
+                clusters.len() - 1
+            });
+            let cluster = &mut clusters[row];
+            cluster.count += 1;
+            if cluster.samples.len() < CLUSTER_SAMPLES
+                && !cluster.samples.contains(&test.test.message)
+            {
+                cluster.samples.push(test.test.message.clone());
+            }
+            cluster.tests.push(FailedTest {
+                file: file.clone(),
+                test: test.long_name(),
+            });
+        }
+    }
+    clusters.sort_by_key(|cluster| Reverse(cluster.count));
+    clusters
+}
@@ -0,0 +1,34 @@
+//! Api key check of the routers
+use hyper::HeaderMap;
+use std::env;
+
+/// Header the api key is read from
+pub const API_KEY_HEADER: &str = "theapikey";
+
+/// How a router checks the api key of a request
+#[derive(Clone, Debug)]
+pub enum Auth {
+    /// Expect this key.
+    ApiKey(String),
+    /// Expect the key held by this environment variable, read on every request.
+    Env(String),
+    /// Accept every request, e.g. behind an auth layer of the embedding application.
+    Disabled,
+}
+
+impl Auth {
+    /// Reason to reject the request. Without `required` a missing key is accepted, a wrong one not.
+    pub(crate) fn check(&self, headers: &HeaderMap, required: bool) -> Result<(), String> {
+        let key = match self {
+            Auth::ApiKey(key) => key.clone(),
+            Auth::Env(name) => env::var(name).map_err(|error| format!("no api key: {error}"))?,
+            Auth::Disabled => return Ok(()),
+        };
+        match headers.get(API_KEY_HEADER) {
+            Some(header) if *header == *key => Ok(()),
+            Some(_) => Err(String::from("incorrect api key")),
+            None if required => Err(String::from("missing api key")),
+            None => Ok(()),
+        }
+    }
+}
@@ -1,125 +1,363 @@
-use axum;
+use crate::analysis;
+use crate::auth::Auth;
+use crate::export;
+use crate::filter::{self, FilterQuery};
+use crate::history::{self, History, Labels};
+use crate::input;
+use crate::merge;
+use crate::ods;
+use crate::report;
+use crate::robot::{self, RobotResult};
+use crate::{DatabaseError, RouterState};
 use axum::debug_handler;
 use axum::{
-    extract::{Path, State},
+    extract::{
+        rejection::{JsonRejection, QueryRejection, StringRejection},
+        Path, Query, State,
+    },
     response,
-    response::IntoResponse,
+    response::{Html, IntoResponse},
     Json,
 };
-use hyper::{HeaderMap, StatusCode};
-use serde::{Deserialize, Serialize};
+use axum_test_client::analysis::{BlendSummary, Comparison, FailureCluster, FlakyTest, Timing};
+use axum_test_client::blend::{
+    BlendError, BlendRequest, CompareInput, CompareRequest, ConvertFormat, RecordLabels,
+};
+use axum_test_client::filter::Filter;
+use axum_test_client::history::{TestOutcome, TrendPoint};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use hyper::{header, HeaderMap, StatusCode};
+use serde::Deserialize;
+use std::cmp::Reverse;
 use std::env;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tracing::debug;
-use utoipa::ToSchema;
+use utoipa::IntoParams;
 use utoipa_axum::{router::OpenApiRouter, routes};
 
 /// In-memory stuff store
 type Store = Mutex<Storage>;
 
+/// Uploaded files and the history database, shared by all blend routes
 #[derive(Clone)]
+pub struct BlendState(Arc<Store>);
+
+impl BlendState {
+    /// State with the history database at `path`, `:memory:` keeps it in memory.
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, DatabaseError> {
+        let history = History::open(path)?;
+        Ok(BlendState(Arc::new(Mutex::new(Storage::new(history)))))
+    }
+
+    /// State with the history database at `HISTORY_DB` or `history.sqlite`.
+    pub fn from_env() -> Result<Self, DatabaseError> {
+        let path = env::var("HISTORY_DB").unwrap_or_else(|_| String::from(DEFAULT_HISTORY_DB));
+        Self::open(path)
+    }
+}
+
 struct Storage {
     blend_storage: Vec<(String, String)>,
+    history: History,
 }
 
 impl Storage {
-    fn new() -> Self {
+    fn new(history: History) -> Self {
         Storage {
             blend_storage: Vec::default(),
+            history,
         }
     }
 }
 
-/// Todo operation errors
-#[derive(Serialize, Deserialize, ToSchema)]
-enum StuffError {
-    /// Already exists conflict.
-    #[schema(example = "Item already exists")]
-    Conflict(String),
-    /// Not found by id.
-    #[schema(example = "id = 1")]
-    NotFound(String),
-    /// Operation unauthorized
-    #[schema(example = "missing api key")]
-    Unauthorized(String),
-}
+/// Location of the history database, overridden by `HISTORY_DB`.
+const DEFAULT_HISTORY_DB: &str = "history.sqlite";
 
-pub(super) fn router() -> OpenApiRouter {
-    let store = Arc::new(Mutex::new(Storage::new()));
+/// Error status with its json body
+type ApiError = (StatusCode, Json<BlendError>);
+
+/// Routes of the blend api on `state`, the api key is checked by `auth`.
+pub fn router(state: BlendState, auth: Auth) -> OpenApiRouter {
+    let state = RouterState { store: state, auth };
     OpenApiRouter::new()
         .routes(routes!(convert_xml))
         .routes(routes!(upload_to_blend))
-        .routes(routes!(blend_files))
-        .routes(routes!(list_to_blend))
-        .with_state(store)
+        .routes(routes!(blend_files, blend_selected))
+        .routes(routes!(summary, summary_selected))
+        .routes(routes!(flaky_tests))
+        .routes(routes!(keyword_timing))
+        .routes(routes!(failure_clusters))
+        .routes(routes!(merge_files))
+        .routes(routes!(blend_report))
+        .routes(routes!(compare))
+        .routes(routes!(compare_report))
+        .routes(routes!(test_history))
+        .routes(routes!(trends))
+        .routes(routes!(list_to_blend, clear_blend))
+        .with_state(state)
+}
+
+/// Media types convert answers with, in the order they are preferred on equal weight.
+const CONVERT_MEDIA_TYPES: [&str; 7] = [
+    "text/plain",
+    "application/json",
+    "text/csv",
+    "application/xml",
+    "text/xml",
+    "application/junit+xml",
+    "text/markdown",
+];
+
+/// Most preferred format of an `Accept` header, ranked by weight, specificity and position as in
+/// RFC 9110 §12.5.1. None without a usable header, 406 if every format is refused.
+fn format_from_accept(headers: &HeaderMap) -> Result<Option<ConvertFormat>, ApiError> {
+    let Some(accept) = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+    else {
+        return Ok(None);
+    };
+    let ranges: Vec<(String, f32)> = accept.split(',').filter_map(media_range).collect();
+    if ranges.is_empty() {
+        return Ok(None);
+    }
+    let mut candidates = Vec::new();
+    for media_type in CONVERT_MEDIA_TYPES {
+        // the most specific range applies, the first one of equal specificity
+        let matching = ranges
+            .iter()
+            .enumerate()
+            .filter_map(|(position, (range, weight))| {
+                let specificity = match range.split_once('/') {
+                    _ if range == media_type => 2,
+                    Some(("*", "*")) => 0,
+                    Some((kind, "*")) if media_type.starts_with(&format!("{kind}/")) => 1,
+                    _ => return None,
+                };
+                Some((specificity, position, *weight))
+            })
+            .min_by_key(|(specificity, position, _)| (Reverse(*specificity), *position));
+        if let Some((specificity, position, weight)) = matching {
+            if weight > 0.0 {
+                candidates.push((weight, specificity, position, media_type));
+            }
+        }
+    }
+    // stable, so equal candidates stay in the order of CONVERT_MEDIA_TYPES
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then(b.1.cmp(&a.1)).then(a.2.cmp(&b.2)));
+    match candidates.first() {
+        Some((_, _, _, media_type)) => Ok(ConvertFormat::from_media_type(media_type)),
+        None => Err((
+            StatusCode::NOT_ACCEPTABLE,
+            Json(BlendError::NotAcceptable(format!("accept: {accept}"))),
+        )),
+    }
+}
+
+/// Lowercase media range and weight of an `Accept` element, None if it is empty or its weight invalid.
+fn media_range(element: &str) -> Option<(String, f32)> {
+    let mut parameters = element.split(';');
+    let range = parameters.next()?.trim().to_lowercase();
+    if range.is_empty() {
+        return None;
+    }
+    let mut weight = 1.0;
+    for parameter in parameters {
+        if let Some((name, value)) = parameter.split_once('=') {
+            if name.trim().eq_ignore_ascii_case("q") {
+                weight = value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|q| (0.0..=1.0).contains(q))?;
+            }
+        }
+    }
+    Some((range, weight))
+}
+
+/// Convert query
+#[derive(Deserialize, IntoParams)]
+struct ConvertQuery {
+    /// Representation of the result, takes precedence over `Accept`. Defaults to `text`.
+    #[param(inline)]
+    format: Option<ConvertFormat>,
 }
 
 /// convert
-#[axum::debug_handler]
+///
+/// Convert a result file. The representation is chosen by `format` or the `Accept` header.
+#[axum::debug_handler(state = RouterState<BlendState>)]
 #[utoipa::path(
         post,
         path = "/xml",
         tag = "blend",
+        params(
+            ConvertQuery,
+            FilterQuery
+        ),
         responses(
-            (status = 200, description = "Call blend_result_parse_from_str_to_str", body = String),
-            // (status = 201, description = "Todo item created successfully", body = String),
-            // (status = 409, description = "Todo already exists", body = String),
+            (status = 200, description = "Converted result", content(
+                (String = "text/plain"),
+                (RobotResult = "application/json"),
+                (String = "text/csv"),
+                (String = "application/xml"),
+                (String = "text/markdown"),
+            )),
+            (status = 400, description = "Invalid query or file could not be parsed", body = BlendError),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
+            (status = 406, description = "No format accepted by `Accept`", body = BlendError),
         ),
-        request_body(content = String, description = "Xml as string request", content_type = "text/xml"),
+        request_body(description = "Robot Framework xml or json, JUnit or xUnit xml as string request",
+             content((String = "text/xml"), (String = "application/json"))),
         security(
             ("api_key" = [])
         ),
     )]
 async fn convert_xml(
-    State(_store): State<Arc<Store>>,
-    //string : Query<String>
-    //Json(val): Json<Val>,
-    //body: String,
+    State(_state): State<BlendState>,
+    State(auth): State<Auth>,
     headers: HeaderMap,
-    string: String, //json : Json<String>
+    query: Result<Query<ConvertQuery>, QueryRejection>,
+    filter: Result<Query<FilterQuery>, QueryRejection>,
+    string: Result<String, StringRejection>,
 ) -> impl IntoResponse {
-    match check_api_key(headers) {
+    let accepted = format_from_accept(&headers);
+    match check_api_key(&auth, headers) {
         Ok(_) => (),
         Err(error) => return error.into_response(),
     }
-    blend_result::parse_from_str_to_str(&string)
-        .unwrap()
-        .to_string()
-        .into_response()
+    let query = match query_params(query) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let filter = match query_params(filter) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let string = match text_body(string) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let format = match (query.format, accepted) {
+        (Some(format), _) => format,
+        (None, Ok(format)) => format.unwrap_or(ConvertFormat::Text),
+        (None, Err(error)) => return error.into_response(),
+    };
+    let xml = match input::normalize(string) {
+        Ok(x) => x,
+        Err(error) => return invalid_input("body", error).into_response(),
+    };
+    let xml = match filter_files(&[String::from("body")], vec![xml], &Filter::from(&filter)) {
+        Ok(mut x) => x.remove(0),
+        Err(error) => return error.into_response(),
+    };
+    match convert(format, &xml) {
+        Ok(x) => x,
+        Err(error) => error.into_response(),
+    }
+}
+
+/// `xml` in the representation of `format`.
+fn convert(format: ConvertFormat, xml: &str) -> Result<response::Response, ApiError> {
+    let (content_type, body) = match format {
+        ConvertFormat::Text => {
+            return blend_result::parse_from_str_to_str(xml)
+                .map(|x| x.to_string().into_response())
+                .map_err(|error| invalid_input("body", error))
+        }
+        ConvertFormat::Json => return Ok(Json(parse_file("body", xml)?).into_response()),
+        ConvertFormat::Csv => ("text/csv", export::to_csv(&parse_file("body", xml)?)),
+        ConvertFormat::Junit => (
+            "application/xml",
+            export::to_junit(&parse_file("body", xml)?),
+        ),
+        ConvertFormat::Markdown => (
+            "text/markdown",
+            export::to_markdown(&parse_file("body", xml)?),
+        ),
+    };
+    Ok(([(header::CONTENT_TYPE, content_type)], body).into_response())
+}
+
+/// Query parameters, rejected ones answered with 400.
+fn query_params<T>(query: Result<Query<T>, QueryRejection>) -> Result<T, ApiError> {
+    query
+        .map(|Query(query)| query)
+        .map_err(|rejection| invalid_input("query", rejection.body_text()))
+}
+
+/// Json body, rejected ones answered with 400.
+fn json_body<T>(body: Result<Json<T>, JsonRejection>) -> Result<T, ApiError> {
+    body.map(|Json(body)| body)
+        .map_err(|rejection| invalid_input("body", rejection.body_text()))
+}
+
+/// Text body, rejected ones answered with 400.
+fn text_body(body: Result<String, StringRejection>) -> Result<String, ApiError> {
+    body.map_err(|rejection| invalid_input("body", rejection.body_text()))
+}
+
+fn invalid_input(name: &str, error: impl std::fmt::Display) -> ApiError {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(BlendError::InvalidInput(format!("{name}: {error}"))),
+    )
 }
 
 /// Upload file to blend
+///
+/// Robot Framework output.json, JUnit and xUnit results are converted to output.xml.
+/// Names are unique, clear the store to upload a file again.
 #[utoipa::path(
         post,
         path = "/upload/{name}",
         tag = "blend",
         responses(
             (status = 200, description = "File uploaded"),
+            (status = 400, description = "Unknown format or file could not be converted", body = BlendError),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
+            (status = 409, description = "A file with this name is already uploaded", body = BlendError),
         ),
         params(
             ("name" = String, Path, description = "Filename")
         ),
-        request_body(content = String, description = "Xml as string request",
-             content_type = "text/xml"),
+        request_body(description = "Robot Framework xml or json, JUnit or xUnit xml as string request",
+             content((String = "text/xml"), (String = "application/json"))),
         security(
             ("api_key" = [])
         ),
     )]
-#[debug_handler]
+#[debug_handler(state = RouterState<BlendState>)]
 async fn upload_to_blend(
     Path(name): Path<String>,
-    State(store): State<Arc<Store>>,
+    State(BlendState(store)): State<BlendState>,
+    State(auth): State<Auth>,
     headers: HeaderMap,
-    data: String,
+    data: Result<String, StringRejection>,
 ) -> response::Response {
-    match check_api_key(headers) {
+    match check_api_key(&auth, headers) {
         Ok(_) => (),
         Err(error) => return error.into_response(),
     }
+    let data = match text_body(data) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let data = match input::normalize(data) {
+        Ok(x) => x,
+        Err(error) => return invalid_input(&name, error).into_response(),
+    };
     let mut state = store.lock().await;
-    // println!("The Request {:?}", data);
-    // println!("The Request Data {:?}", data.len());
+    if state.blend_storage.iter().any(|x| x.0 == name) {
+        return (
+            StatusCode::CONFLICT,
+            Json(BlendError::Conflict(format!("name = {name}"))),
+        )
+            .into_response();
+    }
     state.blend_storage.push((name, data));
     ().into_response()
 }
@@ -130,14 +368,19 @@ async fn upload_to_blend(
         path = "/list",
         tag = "blend",
         responses(
-            (status = 200, description = "List files", body = String),
+            (status = 200, description = "Names of the uploaded files", body = [String]),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
         ),
         security(
             ("api_key" = [])
         ),
     )]
-async fn list_to_blend(State(store): State<Arc<Store>>, headers: HeaderMap) -> response::Response {
-    match check_api_key(headers) {
+async fn list_to_blend(
+    State(BlendState(store)): State<BlendState>,
+    State(auth): State<Auth>,
+    headers: HeaderMap,
+) -> response::Response {
+    match check_api_key(&auth, headers) {
         Ok(_) => (),
         Err(error) => return error.into_response(),
     }
@@ -147,97 +390,900 @@ async fn list_to_blend(State(store): State<Arc<Store>>, headers: HeaderMap) -> r
         .iter()
         .map(|x| x.0.clone())
         .collect::<Vec<String>>();
-    format!("{:?}", files).into_response()
+    Json(files).into_response()
+}
+
+/// Clear files
+///
+/// Remove all uploaded files from the store.
+#[utoipa::path(
+        delete,
+        path = "/list",
+        tag = "blend",
+        responses(
+            (status = 200, description = "Files removed"),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
+        ),
+        security(
+            ("api_key" = [])
+        ),
+    )]
+async fn clear_blend(
+    State(BlendState(store)): State<BlendState>,
+    State(auth): State<Auth>,
+    headers: HeaderMap,
+) -> response::Response {
+    match check_api_key(&auth, headers) {
+        Ok(_) => (),
+        Err(error) => return error.into_response(),
+    }
+    let mut state = store.lock().await;
+    debug!("Clearing {} files", state.blend_storage.len());
+    state.blend_storage = Vec::new();
+    ().into_response()
+}
+
+/// Blend query
+#[derive(Deserialize, IntoParams)]
+struct BlendQuery {
+    /// Remove the uploaded files after a successful blend.
+    #[serde(default)]
+    consume: bool,
 }
 
 /// blend
+///
+/// Blend all uploaded files. The store is left untouched unless `consume` is set.
 #[utoipa::path(
         get,
         path = "/blend",
         tag = "blend",
+        params(
+            BlendQuery,
+            FilterQuery
+        ),
         responses(
             (status = 200, description = "Call blend_results::blend",
                  content_type = "application/octet-stream"),
-            (status = 400, description = "Errror" ),
+            (status = 400, description = "Invalid query or files could not be blended", body = BlendError),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
+            (status = 500, description = "Blend could not be exported", body = BlendError),
         ),
         security(
             ("api_key" = [])
         ),
     )]
-async fn blend_files(State(store): State<Arc<Store>>, headers: HeaderMap) -> response::Response {
-    let mut state = store.lock().await;
-    match check_api_key(headers) {
+async fn blend_files(
+    State(BlendState(store)): State<BlendState>,
+    State(auth): State<Auth>,
+    headers: HeaderMap,
+    query: Result<Query<BlendQuery>, QueryRejection>,
+    filter: Result<Query<FilterQuery>, QueryRejection>,
+) -> response::Response {
+    match check_api_key(&auth, headers) {
         Ok(_) => (),
         Err(error) => return error.into_response(),
     }
-    // println!("The Request {}", string);
-    let files = state
-        .blend_storage
-        .iter()
-        .map(|x| x.0.clone())
-        .collect::<Vec<String>>();
-    let data = state
-        .blend_storage
-        .iter()
-        .map(|x| x.1.clone())
-        .collect::<Vec<String>>();
-    let mrl = match blend_result::blend_results::blend(&data, &files, 5) {
+    let query = match query_params(query) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let filter = match query_params(filter) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let request = BlendRequest {
+        filter: Filter::from(&filter),
+        ..BlendRequest::default()
+    };
+    blend_stored(&store, &request, query.consume).await
+}
+
+/// blend with parameters
+///
+/// Blend a selection of the uploaded files in the requested column order.
+#[utoipa::path(
+        post,
+        path = "/blend",
+        tag = "blend",
+        params(
+            BlendQuery
+        ),
+        request_body = BlendRequest,
+        responses(
+            (status = 200, description = "Call blend_results::blend",
+                 content_type = "application/octet-stream"),
+            (status = 400, description = "Invalid request, files could not be blended or invalid record timestamp", body = BlendError),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
+            (status = 404, description = "File not uploaded", body = BlendError),
+            (status = 409, description = "Duplicate column", body = BlendError),
+            (status = 500, description = "Blend could not be exported or recorded", body = BlendError),
+        ),
+        security(
+            ("api_key" = [])
+        ),
+    )]
+async fn blend_selected(
+    State(BlendState(store)): State<BlendState>,
+    State(auth): State<Auth>,
+    headers: HeaderMap,
+    query: Result<Query<BlendQuery>, QueryRejection>,
+    request: Result<Json<BlendRequest>, JsonRejection>,
+) -> response::Response {
+    match check_api_key(&auth, headers) {
+        Ok(_) => (),
+        Err(error) => return error.into_response(),
+    }
+    let query = match query_params(query) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let request = match json_body(request) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    blend_stored(&store, &request, query.consume).await
+}
+
+async fn blend_stored(store: &Store, request: &BlendRequest, consume: bool) -> response::Response {
+    let timestamp = match request.record.as_ref().map(record_timestamp).transpose() {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let mut state = store.lock().await;
+    let (files, data) = match select_files(&state.blend_storage, request) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let results = parse_files(&files, &data);
+    let result = match spreadsheet(&files, &data, request.depth, results.as_deref().ok()) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    if let (Some(record), Some(timestamp)) = (&request.record, timestamp) {
+        let results = match results {
+            Ok(x) => x,
+            Err(error) => return error.into_response(),
+        };
+        let labels = Labels {
+            project: record.project.clone(),
+            branch: record.branch.clone(),
+            timestamp,
+        };
+        match state.history.record(labels, files, results).await {
+            Ok(blend) => debug!("Recorded blend {blend} of {}", record.project),
+            Err(error) => return database_error(error).into_response(),
+        }
+    }
+    debug!("The Reponse has len {}", result.len());
+    if consume {
+        state.blend_storage = Vec::new();
+    }
+
+    result.into_response()
+}
+
+/// Blended spreadsheet, with the analysis sheets when the files could be parsed into `results`.
+fn spreadsheet(
+    files: &[String],
+    data: &[String],
+    depth: usize,
+    results: Option<&[RobotResult]>,
+) -> Result<Vec<u8>, ApiError> {
+    let mrl = match blend_result::blend_results::blend(data, files, depth) {
         Ok(x) => x,
         Err(error) => {
             debug!("Error while blending");
-            return error.to_string().as_bytes().to_owned().into_response();
+            return Err(invalid_input("blend", error));
         }
     };
     let result = match mrl.export_to_ods() {
         Ok(x) => x,
         Err(error) => {
             debug!("Error while exporing");
-            return error.to_string().as_bytes().to_owned().into_response();
+            return Err(export_error(error));
         }
     };
-    // let result = match String::from_utf8(result) {
-    //     Ok(x) => x,
-    //     Err(error) => {
-    //         debug!("Error while converting");
-    //         return error.to_string();
-    //     }
-    // };
-    debug!("The Reponse has len {}", result.len());
-    state.blend_storage = Vec::new();
+    let Some(results) = results else {
+        debug!("Skipping analysis sheets, files could not be parsed");
+        return Ok(result);
+    };
+    let flaky = analysis::find_flaky(files, results);
+    let timing = analysis::timing(files, results, DEFAULT_TIMING_TOP);
+    let sheets = vec![
+        ("Flaky", analysis::flaky_sheet(&flaky)),
+        ("Keyword Timing", analysis::keyword_timing_sheet(&timing)),
+        ("Slowest", analysis::slowest_sheet(&timing)),
+    ];
+    ods::append_sheets(&result, sheets).map_err(|error| {
+        debug!("Error while adding analysis sheets");
+        export_error(error)
+    })
+}
 
-    result.into_response()
-    //Json(results)
+/// Time to record a blend with, the current time when not given.
+fn record_timestamp(record: &RecordLabels) -> Result<DateTime<Utc>, ApiError> {
+    match &record.timestamp {
+        Some(timestamp) => DateTime::parse_from_rfc3339(timestamp)
+            .map(|timestamp| timestamp.with_timezone(&Utc))
+            .map_err(|error| invalid_input("timestamp", error)),
+        None => Ok(Utc::now()),
+    }
 }
 
-// normally you should create a middleware for this but this is sufficient for sake of example.
-fn check_api_key(headers: HeaderMap) -> Result<(), (StatusCode, Json<StuffError>)> {
-    let key = match env::var("API_KEY") {
-        Ok(key) => key,
-        Err(e) => {
+/// Pick the files to blend, returning their column titles and contents.
+fn select_files(
+    storage: &[(String, String)],
+    request: &BlendRequest,
+) -> Result<(Vec<String>, Vec<String>), ApiError> {
+    if request.files.is_empty() {
+        let (files, data): (Vec<String>, Vec<String>) = storage.iter().cloned().unzip();
+        let data = filter_files(&files, data, &request.filter)?;
+        return Ok((files, data));
+    }
+    let mut files = Vec::new();
+    let mut data = Vec::new();
+    for file in &request.files {
+        let title = file.display_name.as_ref().unwrap_or(&file.name);
+        if files.contains(title) {
             return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(StuffError::Unauthorized(String::from(format!(
-                    "no api key: {e}"
-                )))),
-            ))
+                StatusCode::CONFLICT,
+                Json(BlendError::Conflict(format!("duplicate column: {title}"))),
+            ));
         }
-    };
-    match headers.get("theapikey") {
-        Some(header) => {
-            if *header == *key {
-                Ok(())
-            } else {
-                Err((
-                    StatusCode::UNAUTHORIZED,
-                    Json(StuffError::Unauthorized(String::from("incorrect api key"))),
+        match storage.iter().find(|x| x.0 == file.name) {
+            Some(x) => {
+                files.push(title.clone());
+                data.push(x.1.clone());
+            }
+            None => {
+                return Err((
+                    StatusCode::NOT_FOUND,
+                    Json(BlendError::NotFound(format!("name = {}", file.name))),
                 ))
             }
         }
-        None => Err((
-            StatusCode::UNAUTHORIZED,
-            Json(StuffError::Unauthorized(String::from(
-                "missing api key in request",
-            ))),
+    }
+    let data = filter_files(&files, data, &request.filter)?;
+    Ok((files, data))
+}
+
+/// Cut the tests not selected by `filter` out of the files.
+fn filter_files(
+    files: &[String],
+    data: Vec<String>,
+    filter: &Filter,
+) -> Result<Vec<String>, ApiError> {
+    if filter.is_empty() {
+        return Ok(data);
+    }
+    files
+        .iter()
+        .zip(&data)
+        .map(|(name, xml)| {
+            filter::filter_output_xml(xml, filter).map_err(|error| invalid_input(name, error))
+        })
+        .collect()
+}
+
+/// Parse the selected files for analysis.
+fn parse_files(files: &[String], data: &[String]) -> Result<Vec<RobotResult>, ApiError> {
+    files
+        .iter()
+        .zip(data)
+        .map(|(name, xml)| parse_file(name, xml))
+        .collect()
+}
+
+fn parse_file(name: &str, xml: &str) -> Result<RobotResult, ApiError> {
+    robot::parse_output_xml(xml).map_err(|error| invalid_input(name, error))
+}
+
+/// Summary
+///
+/// Test counts per file and tests whose status changed between the uploaded files.
+#[utoipa::path(
+        get,
+        path = "/summary",
+        tag = "blend",
+        params(
+            FilterQuery
+        ),
+        responses(
+            (status = 200, description = "Blend summary", body = BlendSummary),
+            (status = 400, description = "Invalid query or file could not be parsed", body = BlendError),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
+        ),
+        security(
+            ("api_key" = [])
+        ),
+    )]
+async fn summary(
+    State(BlendState(store)): State<BlendState>,
+    State(auth): State<Auth>,
+    headers: HeaderMap,
+    filter: Result<Query<FilterQuery>, QueryRejection>,
+) -> response::Response {
+    match check_api_key(&auth, headers) {
+        Ok(_) => (),
+        Err(error) => return error.into_response(),
+    }
+    let filter = match query_params(filter) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let request = BlendRequest {
+        filter: Filter::from(&filter),
+        ..BlendRequest::default()
+    };
+    summarize_stored(&store, &request).await
+}
+
+/// Summary with parameters
+///
+/// Summary over a selection of the uploaded files in the requested column order.
+#[utoipa::path(
+        post,
+        path = "/summary",
+        tag = "blend",
+        request_body = BlendRequest,
+        responses(
+            (status = 200, description = "Blend summary", body = BlendSummary),
+            (status = 400, description = "Invalid request or file could not be parsed", body = BlendError),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
+            (status = 404, description = "File not uploaded", body = BlendError),
+            (status = 409, description = "Duplicate column", body = BlendError),
+        ),
+        security(
+            ("api_key" = [])
+        ),
+    )]
+async fn summary_selected(
+    State(BlendState(store)): State<BlendState>,
+    State(auth): State<Auth>,
+    headers: HeaderMap,
+    request: Result<Json<BlendRequest>, JsonRejection>,
+) -> response::Response {
+    match check_api_key(&auth, headers) {
+        Ok(_) => (),
+        Err(error) => return error.into_response(),
+    }
+    let request = match json_body(request) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    summarize_stored(&store, &request).await
+}
+
+async fn summarize_stored(store: &Store, request: &BlendRequest) -> response::Response {
+    let state = store.lock().await;
+    let summary = select_files(&state.blend_storage, request)
+        .and_then(|(files, data)| Ok(analysis::summarize(&files, &parse_files(&files, &data)?)));
+    match summary {
+        Ok(summary) => Json(summary).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+/// Flaky tests
+///
+/// Tests that passed in some of the uploaded runs and failed in others.
+#[utoipa::path(
+        get,
+        path = "/flaky",
+        tag = "blend",
+        responses(
+            (status = 200, description = "Flaky tests, flakiest first", body = [FlakyTest]),
+            (status = 400, description = "File could not be parsed", body = BlendError),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
+        ),
+        security(
+            ("api_key" = [])
+        ),
+    )]
+async fn flaky_tests(
+    State(BlendState(store)): State<BlendState>,
+    State(auth): State<Auth>,
+    headers: HeaderMap,
+) -> response::Response {
+    match check_api_key(&auth, headers) {
+        Ok(_) => (),
+        Err(error) => return error.into_response(),
+    }
+    let state = store.lock().await;
+    let (files, data): (Vec<String>, Vec<String>) = state.blend_storage.iter().cloned().unzip();
+    match parse_files(&files, &data) {
+        Ok(results) => Json(analysis::find_flaky(&files, &results)).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+/// Number of slowest tests and keywords listed per file by default.
+const DEFAULT_TIMING_TOP: usize = 10;
+
+/// Timing query
+#[derive(Deserialize, IntoParams)]
+struct TimingQuery {
+    /// Number of slowest tests and keywords listed per file.
+    #[serde(default = "default_timing_top")]
+    #[param(default = 10)]
+    top: usize,
+}
+
+fn default_timing_top() -> usize {
+    DEFAULT_TIMING_TOP
+}
+
+/// Keyword timing
+///
+/// Keyword run time statistics over the uploaded files and the slowest tests and keywords per file.
+#[utoipa::path(
+        get,
+        path = "/timing",
+        tag = "blend",
+        params(
+            TimingQuery,
+            FilterQuery
+        ),
+        responses(
+            (status = 200, description = "Keyword timing", body = Timing),
+            (status = 400, description = "Invalid query or file could not be parsed", body = BlendError),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
+        ),
+        security(
+            ("api_key" = [])
+        ),
+    )]
+async fn keyword_timing(
+    State(BlendState(store)): State<BlendState>,
+    State(auth): State<Auth>,
+    headers: HeaderMap,
+    query: Result<Query<TimingQuery>, QueryRejection>,
+    filter: Result<Query<FilterQuery>, QueryRejection>,
+) -> response::Response {
+    match check_api_key(&auth, headers) {
+        Ok(_) => (),
+        Err(error) => return error.into_response(),
+    }
+    let query = match query_params(query) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let filter = match query_params(filter) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let state = store.lock().await;
+    let request = BlendRequest {
+        filter: Filter::from(&filter),
+        ..BlendRequest::default()
+    };
+    let timing = select_files(&state.blend_storage, &request).and_then(|(files, data)| {
+        Ok(analysis::timing(
+            &files,
+            &parse_files(&files, &data)?,
+            query.top,
+        ))
+    });
+    match timing {
+        Ok(timing) => Json(timing).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+/// Failure clusters
+///
+/// Failing tests of the uploaded files grouped by failure message, with numbers,
+/// paths and UUIDs masked. The largest cluster comes first.
+#[utoipa::path(
+        get,
+        path = "/clusters",
+        tag = "blend",
+        params(
+            FilterQuery
+        ),
+        responses(
+            (status = 200, description = "Failure clusters", body = [FailureCluster]),
+            (status = 400, description = "Invalid query or file could not be parsed", body = BlendError),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
+        ),
+        security(
+            ("api_key" = [])
+        ),
+    )]
+async fn failure_clusters(
+    State(BlendState(store)): State<BlendState>,
+    State(auth): State<Auth>,
+    headers: HeaderMap,
+    filter: Result<Query<FilterQuery>, QueryRejection>,
+) -> response::Response {
+    match check_api_key(&auth, headers) {
+        Ok(_) => (),
+        Err(error) => return error.into_response(),
+    }
+    let filter = match query_params(filter) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let state = store.lock().await;
+    let request = BlendRequest {
+        filter: Filter::from(&filter),
+        ..BlendRequest::default()
+    };
+    let clusters = select_files(&state.blend_storage, &request).and_then(|(files, data)| {
+        Ok(analysis::cluster_failures(
+            &files,
+            &parse_files(&files, &data)?,
+        ))
+    });
+    match clusters {
+        Ok(clusters) => Json(clusters).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+/// Media type of the blended spreadsheet.
+const ODS_MEDIA_TYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+/// Blend as html
+///
+/// Table of all uploaded files with sorting and filtering by suite and status.
+/// The blended spreadsheet is embedded for download.
+#[utoipa::path(
+        get,
+        path = "/report",
+        tag = "blend",
+        params(
+            FilterQuery
+        ),
+        responses(
+            (status = 200, description = "Blend report", body = String, content_type = "text/html"),
+            (status = 400, description = "Invalid query or file could not be parsed", body = BlendError),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
+        ),
+        security(
+            ("api_key" = [])
+        ),
+    )]
+async fn blend_report(
+    State(BlendState(store)): State<BlendState>,
+    State(auth): State<Auth>,
+    headers: HeaderMap,
+    filter: Result<Query<FilterQuery>, QueryRejection>,
+) -> response::Response {
+    match check_api_key(&auth, headers) {
+        Ok(_) => (),
+        Err(error) => return error.into_response(),
+    }
+    let filter = match query_params(filter) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let state = store.lock().await;
+    let request = BlendRequest {
+        filter: Filter::from(&filter),
+        ..BlendRequest::default()
+    };
+    let (files, data) = match select_files(&state.blend_storage, &request) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let results = match parse_files(&files, &data) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    // Embedded, a link to the blend endpoint could not send the api key.
+    let download = match spreadsheet(&files, &data, request.depth, Some(&results)) {
+        Ok(ods) => Some(format!(
+            "data:{ODS_MEDIA_TYPE};base64,{}",
+            BASE64.encode(ods)
         )),
+        Err((_, Json(error))) => {
+            debug!("Report without spreadsheet: {error:?}");
+            None
+        }
+    };
+    Html(report::blend(&files, &results, download.as_deref())).into_response()
+}
+
+/// Merge
+///
+/// Merge the uploaded files in upload order into one output.xml, like `rebot --merge`.
+/// Tests in later files replace the same tests in earlier ones unless they were skipped.
+/// All files need the same root suite.
+#[utoipa::path(
+        get,
+        path = "/merge",
+        tag = "blend",
+        params(
+            FilterQuery
+        ),
+        responses(
+            (status = 200, description = "Merged output.xml", body = String, content_type = "application/xml"),
+            (status = 400, description = "Invalid query, file could not be parsed or root suites differ", body = BlendError),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
+            (status = 404, description = "No files uploaded", body = BlendError),
+        ),
+        security(
+            ("api_key" = [])
+        ),
+    )]
+async fn merge_files(
+    State(BlendState(store)): State<BlendState>,
+    State(auth): State<Auth>,
+    headers: HeaderMap,
+    filter: Result<Query<FilterQuery>, QueryRejection>,
+) -> response::Response {
+    match check_api_key(&auth, headers) {
+        Ok(_) => (),
+        Err(error) => return error.into_response(),
+    }
+    let filter = match query_params(filter) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let state = store.lock().await;
+    let (files, data): (Vec<String>, Vec<String>) = state.blend_storage.iter().cloned().unzip();
+    let filter = Filter::from(&filter);
+    let results = match parse_files(&files, &data) {
+        Ok(x) if filter.is_empty() => x,
+        Ok(x) => x.into_iter().map(|result| filter.apply(result)).collect(),
+        Err(error) => return error.into_response(),
+    };
+    match merge::merge(results) {
+        Ok(Some(merged)) => (
+            [(header::CONTENT_TYPE, "application/xml")],
+            robot::to_output_xml(&merged),
+        )
+            .into_response(),
+        Err(error) => invalid_input("merge", error).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(BlendError::NotFound(String::from("no files uploaded"))),
+        )
+            .into_response(),
+    }
+}
+
+/// Name and parsed result of a run to compare.
+fn read_compare_input(
+    storage: &[(String, String)],
+    input: &CompareInput,
+    default_name: &str,
+) -> Result<(String, RobotResult), ApiError> {
+    let (name, xml) = match input {
+        CompareInput::Stored(name) => match storage.iter().find(|x| x.0 == *name) {
+            Some(x) => (name.as_str(), x.1.clone()),
+            None => {
+                return Err((
+                    StatusCode::NOT_FOUND,
+                    Json(BlendError::NotFound(format!("name = {name}"))),
+                ))
+            }
+        },
+        // converted like an upload
+        CompareInput::Xml(data) => match input::normalize(data.clone()) {
+            Ok(x) => (default_name, x),
+            Err(error) => return Err(invalid_input(default_name, error)),
+        },
+    };
+    Ok((name.to_string(), parse_file(name, &xml)?))
+}
+
+async fn compare_stored(
+    store: &Store,
+    request: &CompareRequest,
+) -> Result<(String, String, Comparison), ApiError> {
+    let state = store.lock().await;
+    let (baseline_name, baseline) =
+        read_compare_input(&state.blend_storage, &request.baseline, "baseline")?;
+    let (current_name, current) =
+        read_compare_input(&state.blend_storage, &request.current, "current")?;
+    let comparison = analysis::compare(&baseline, &current, request.duration_threshold);
+    Ok((baseline_name, current_name, comparison))
+}
+
+/// Compare
+///
+/// Differences between a baseline and a current run.
+#[utoipa::path(
+        post,
+        path = "/compare",
+        tag = "blend",
+        request_body = CompareRequest,
+        responses(
+            (status = 200, description = "Comparison", body = Comparison),
+            (status = 400, description = "Invalid request or file could not be parsed", body = BlendError),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
+            (status = 404, description = "File not uploaded", body = BlendError),
+        ),
+        security(
+            ("api_key" = [])
+        ),
+    )]
+async fn compare(
+    State(BlendState(store)): State<BlendState>,
+    State(auth): State<Auth>,
+    headers: HeaderMap,
+    request: Result<Json<CompareRequest>, JsonRejection>,
+) -> response::Response {
+    match check_api_key(&auth, headers) {
+        Ok(_) => (),
+        Err(error) => return error.into_response(),
+    }
+    let request = match json_body(request) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    match compare_stored(&store, &request).await {
+        Ok((_, _, comparison)) => Json(comparison).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+/// Compare as html
+///
+/// Differences between a baseline and a current run as html report.
+#[utoipa::path(
+        post,
+        path = "/compare/report",
+        tag = "blend",
+        request_body = CompareRequest,
+        responses(
+            (status = 200, description = "Comparison report", body = String, content_type = "text/html"),
+            (status = 400, description = "Invalid request or file could not be parsed", body = BlendError),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
+            (status = 404, description = "File not uploaded", body = BlendError),
+        ),
+        security(
+            ("api_key" = [])
+        ),
+    )]
+async fn compare_report(
+    State(BlendState(store)): State<BlendState>,
+    State(auth): State<Auth>,
+    headers: HeaderMap,
+    request: Result<Json<CompareRequest>, JsonRejection>,
+) -> response::Response {
+    match check_api_key(&auth, headers) {
+        Ok(_) => (),
+        Err(error) => return error.into_response(),
+    }
+    let request = match json_body(request) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    match compare_stored(&store, &request).await {
+        Ok((baseline, current, comparison)) => {
+            Html(report::comparison(&baseline, &current, &comparison)).into_response()
+        }
+        Err(error) => error.into_response(),
     }
 }
+
+/// History query
+#[derive(Deserialize, IntoParams)]
+struct HistoryQuery {
+    /// Only blends recorded for this branch.
+    branch: Option<String>,
+}
+
+/// Test history
+///
+/// Outcomes of a test over the recorded blends of a project, oldest first.
+#[utoipa::path(
+        get,
+        path = "/history/{project}/tests/{test}",
+        tag = "blend",
+        params(
+            ("project" = String, Path, description = "Project the blends were recorded for"),
+            ("test" = String, Path, description = "Long name of the test"),
+            HistoryQuery
+        ),
+        responses(
+            (status = 200, description = "Test outcomes", body = [TestOutcome]),
+            (status = 400, description = "Invalid query", body = BlendError),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
+            (status = 500, description = "History database error", body = BlendError),
+        ),
+        security(
+            ("api_key" = [])
+        ),
+    )]
+async fn test_history(
+    Path((project, test)): Path<(String, String)>,
+    State(BlendState(store)): State<BlendState>,
+    State(auth): State<Auth>,
+    headers: HeaderMap,
+    query: Result<Query<HistoryQuery>, QueryRejection>,
+) -> response::Response {
+    match check_api_key(&auth, headers) {
+        Ok(_) => (),
+        Err(error) => return error.into_response(),
+    }
+    let query = match query_params(query) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let history = store.lock().await.history.clone();
+    match history.test_history(project, query.branch, test).await {
+        Ok(outcomes) => Json(outcomes).into_response(),
+        Err(error) => database_error(error).into_response(),
+    }
+}
+
+/// Trend query
+#[derive(Deserialize, IntoParams)]
+struct TrendQuery {
+    /// Only blends recorded for this branch.
+    branch: Option<String>,
+    /// Only count the outcomes of this test.
+    test: Option<String>,
+}
+
+/// Trends
+///
+/// Pass rate per recorded blend of a project, oldest first.
+#[utoipa::path(
+        get,
+        path = "/history/{project}/trends",
+        tag = "blend",
+        params(
+            ("project" = String, Path, description = "Project the blends were recorded for"),
+            TrendQuery
+        ),
+        responses(
+            (status = 200, description = "Outcome counts per blend", body = [TrendPoint]),
+            (status = 400, description = "Invalid query", body = BlendError),
+            (status = 401, description = "Missing or incorrect api key", body = BlendError),
+            (status = 500, description = "History database error", body = BlendError),
+        ),
+        security(
+            ("api_key" = [])
+        ),
+    )]
+async fn trends(
+    Path(project): Path<String>,
+    State(BlendState(store)): State<BlendState>,
+    State(auth): State<Auth>,
+    headers: HeaderMap,
+    query: Result<Query<TrendQuery>, QueryRejection>,
+) -> response::Response {
+    match check_api_key(&auth, headers) {
+        Ok(_) => (),
+        Err(error) => return error.into_response(),
+    }
+    let query = match query_params(query) {
+        Ok(x) => x,
+        Err(error) => return error.into_response(),
+    };
+    let history = store.lock().await.history.clone();
+    match history.trends(project, query.branch, query.test).await {
+        Ok(trend) => Json(trend).into_response(),
+        Err(error) => database_error(error).into_response(),
+    }
+}
+
+fn database_error(error: history::Error) -> ApiError {
+    debug!("History database error: {error}");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(BlendError::Database(error.to_string())),
+    )
+}
+
+fn export_error(error: impl std::fmt::Display) -> ApiError {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(BlendError::Export(error.to_string())),
+    )
+}
+
+fn check_api_key(auth: &Auth, headers: HeaderMap) -> Result<(), ApiError> {
+    auth.check(&headers, true).map_err(|error| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(BlendError::Unauthorized(error)),
+        )
+    })
+}
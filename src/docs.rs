@@ -0,0 +1,84 @@
+//! Documentation UIs of the OpenAPI document
+//!
+//! Swagger UI is always built in, Redoc, RapiDoc and Scalar with the cargo features
+//! `redoc`, `rapidoc` and `scalar`.
+use axum::{routing::get, Json, Router};
+use std::env;
+use utoipa::openapi::OpenApi;
+#[cfg(feature = "rapidoc")]
+use utoipa_rapidoc::RapiDoc;
+#[cfg(feature = "redoc")]
+use utoipa_redoc::{Redoc, Servable};
+#[cfg(feature = "scalar")]
+use utoipa_scalar::{Scalar, Servable as ScalarServable};
+use utoipa_swagger_ui::{Config, SwaggerUi};
+
+/// Path of the OpenAPI document, served even with the UIs disabled.
+pub const OPENAPI_PATH: &str = "/api-docs/openapi.json";
+
+/// Which documentation UIs are served below which paths
+#[derive(Clone, Debug)]
+pub struct Docs {
+    /// Serve the UIs. Off in production, the OpenAPI document stays available.
+    pub enabled: bool,
+    pub swagger_ui: String,
+    /// Used with the `redoc` feature.
+    pub redoc: String,
+    /// Used with the `rapidoc` feature.
+    pub rapidoc: String,
+    /// Used with the `scalar` feature.
+    pub scalar: String,
+}
+
+impl Default for Docs {
+    fn default() -> Self {
+        Docs {
+            enabled: true,
+            swagger_ui: String::from("/swagger-ui"),
+            redoc: String::from("/redoc"),
+            rapidoc: String::from("/rapidoc"),
+            scalar: String::from("/scalar"),
+        }
+    }
+}
+
+impl Docs {
+    /// The defaults, overridden by `DOCS_UI=off` and the paths in `SWAGGER_UI_PATH`,
+    /// `REDOC_PATH`, `RAPIDOC_PATH` and `SCALAR_PATH`.
+    pub fn from_env() -> Self {
+        let default = Docs::default();
+        let path = |name: &str, default: String| env::var(name).unwrap_or(default);
+        Docs {
+            enabled: !matches!(env::var("DOCS_UI").as_deref(), Ok("off" | "false" | "0")),
+            swagger_ui: path("SWAGGER_UI_PATH", default.swagger_ui),
+            redoc: path("REDOC_PATH", default.redoc),
+            rapidoc: path("RAPIDOC_PATH", default.rapidoc),
+            scalar: path("SCALAR_PATH", default.scalar),
+        }
+    }
+
+    /// Router serving `api` at [`OPENAPI_PATH`] and the enabled UIs.
+    pub fn router(&self, api: OpenApi) -> Router {
+        let document = api.clone();
+        let router = Router::new().route(
+            OPENAPI_PATH,
+            get(move || {
+                let document = document.clone();
+                async move { Json(document) }
+            }),
+        );
+        if !self.enabled {
+            return router;
+        }
+        let router = router
+            .merge(SwaggerUi::new(self.swagger_ui.clone()).config(Config::new([OPENAPI_PATH])));
+        #[cfg(feature = "redoc")]
+        let router = router.merge(Redoc::with_url(self.redoc.clone(), api.clone()));
+        // The document is already served, RapiDoc only points to it.
+        #[cfg(feature = "rapidoc")]
+        let router = router.merge(RapiDoc::new(OPENAPI_PATH).path(self.rapidoc.clone()));
+        #[cfg(feature = "scalar")]
+        let router = router.merge(Scalar::with_url(self.scalar.clone(), api));
+        router
+    }
+}
@@ -0,0 +1,139 @@
+//! Representations of a single result offered by the convert endpoint
+use crate::robot::{RobotResult, Status, Suite};
+use quick_xml::escape::escape;
+use std::fmt::Write;
+
+/// One row per test: suite, test, status, elapsed seconds and message.
+pub(crate) fn to_csv(result: &RobotResult) -> String {
+    let mut csv = String::from("suite,test,status,elapsed,message\r\n");
+    for test in result.tests() {
+        let elapsed = test
+            .test
+            .elapsed
+            .map(|elapsed| elapsed.to_string())
+            .unwrap_or_default();
+        let _ = write!(
+            csv,
+            "{},{},{},{elapsed},{}\r\n",
+            csv_field(&test.suite),
+            csv_field(&test.test.name),
+            test.test.status.as_str(),
+            csv_field(&test.test.message)
+        );
+    }
+    csv
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// JUnit XML with one testsuite per suite holding tests.
+pub(crate) fn to_junit(result: &RobotResult) -> String {
+    let tests = result.tests();
+    let failures = tests
+        .iter()
+        .filter(|test| test.test.status == Status::Fail)
+        .count();
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <testsuites name=\"{}\" tests=\"{}\" failures=\"{failures}\">\n",
+        escape(&result.suite.name),
+        tests.len()
+    );
+    write_testsuites(&mut xml, &result.suite, &result.suite.name);
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+fn write_testsuites(xml: &mut String, suite: &Suite, long_name: &str) {
+    if !suite.tests.is_empty() {
+        let count = |status| suite.tests.iter().filter(|t| t.status == status).count();
+        let _ = writeln!(
+            xml,
+            "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\"{}>",
+            escape(long_name),
+            suite.tests.len(),
+            count(Status::Fail),
+            count(Status::Skip) + count(Status::NotRun),
+            time_attribute(suite.elapsed)
+        );
+        for test in &suite.tests {
+            let _ = write!(
+                xml,
+                "<testcase classname=\"{}\" name=\"{}\"{}",
+                escape(long_name),
+                escape(&test.name),
+                time_attribute(test.elapsed)
+            );
+            match test.status {
+                Status::Pass => xml.push_str("/>\n"),
+                Status::Fail => {
+                    let _ = writeln!(
+                        xml,
+                        ">\n<failure message=\"{}\"/>\n</testcase>",
+                        escape(&test.message)
+                    );
+                }
+                Status::Skip | Status::NotRun => {
+                    let _ = writeln!(
+                        xml,
+                        ">\n<skipped message=\"{}\"/>\n</testcase>",
+                        escape(&test.message)
+                    );
+                }
+            }
+        }
+        xml.push_str("</testsuite>\n");
+    }
+    for child in &suite.suites {
+        write_testsuites(xml, child, &format!("{long_name}.{}", child.name));
+    }
+}
+
+fn time_attribute(elapsed: Option<f64>) -> String {
+    match elapsed {
+        Some(elapsed) => format!(" time=\"{elapsed:.3}\""),
+        None => String::new(),
+    }
+}
+
+/// Markdown with the outcome counts and a table of all tests.
+pub(crate) fn to_markdown(result: &RobotResult) -> String {
+    let tests = result.tests();
+    let count = |status| tests.iter().filter(|t| t.test.status == status).count();
+    let mut markdown = format!(
+        "# {}\n\n{} tests, {} passed, {} failed, {} skipped\n\n\
+         | Suite | Test | Status | Elapsed [s] | Message |\n\
+         | --- | --- | --- | ---: | --- |\n",
+        markdown_cell(&result.suite.name),
+        tests.len(),
+        count(Status::Pass),
+        count(Status::Fail),
+        count(Status::Skip)
+    );
+    for test in &tests {
+        let elapsed = test
+            .test
+            .elapsed
+            .map(|elapsed| format!("{elapsed:.3}"))
+            .unwrap_or_default();
+        let _ = writeln!(
+            markdown,
+            "| {} | {} | {} | {elapsed} | {} |",
+            markdown_cell(&test.suite),
+            markdown_cell(&test.test.name),
+            test.test.status.as_str(),
+            markdown_cell(&test.test.message)
+        );
+    }
+    markdown
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
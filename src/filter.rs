@@ -0,0 +1,338 @@
+//! Filter patterns given as query parameters, applied to output.xml files
+//!
+//! The patterns and their matching live in `axum_test_client::filter`. Filtered
+//! files keep everything the model does not read, such as arguments, messages
+//! and documentation, as only the dropped elements are cut out of them.
+use crate::robot::{self, ParseError, Suite};
+use axum_test_client::filter::Filter;
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+/// Filter patterns as query, comma separated
+#[derive(Deserialize, IntoParams, Default)]
+pub(crate) struct FilterQuery {
+    /// Suites to keep, e.g. `Tests.Login*`.
+    include_suites: Option<String>,
+    exclude_suites: Option<String>,
+    /// Tests to keep.
+    include_tests: Option<String>,
+    exclude_tests: Option<String>,
+    /// Tags to keep, e.g. `smoke,critical`.
+    include_tags: Option<String>,
+    exclude_tags: Option<String>,
+}
+
+impl From<&FilterQuery> for Filter {
+    fn from(query: &FilterQuery) -> Self {
+        let split = |patterns: &Option<String>| -> Vec<String> {
+            patterns
+                .iter()
+                .flat_map(|patterns| patterns.split(','))
+                .map(str::trim)
+                .filter(|pattern| !pattern.is_empty())
+                .map(String::from)
+                .collect()
+        };
+        Filter {
+            include_suites: split(&query.include_suites),
+            exclude_suites: split(&query.exclude_suites),
+            include_tests: split(&query.include_tests),
+            exclude_tests: split(&query.exclude_tests),
+            include_tags: split(&query.include_tags),
+            exclude_tags: split(&query.exclude_tags),
+        }
+    }
+}
+
+/// Which children of a suite are kept, in document order.
+struct Selection {
+    /// Whether a test of the suite or of its children is kept.
+    keep: bool,
+    suites: Vec<Selection>,
+    tests: Vec<bool>,
+}
+
+impl Selection {
+    fn new(filter: &Filter, suite: &Suite, path: &mut Vec<String>) -> Self {
+        path.push(suite.name.clone());
+        let suites: Vec<Selection> = suite
+            .suites
+            .iter()
+            .map(|child| Selection::new(filter, child, path))
+            .collect();
+        let tests: Vec<bool> = suite
+            .tests
+            .iter()
+            .map(|test| filter.keeps(path, test))
+            .collect();
+        path.pop();
+        Selection {
+            keep: tests.contains(&true) || suites.iter().any(|child| child.keep),
+            suites,
+            tests,
+        }
+    }
+
+    fn keeps_all(&self) -> bool {
+        self.tests.iter().all(|keep| *keep) && self.suites.iter().all(Selection::keeps_all)
+    }
+}
+
+/// A suite entered while reading, with the children seen and kept so far.
+struct Frame<'a> {
+    selection: &'a Selection,
+    id: String,
+    suites: usize,
+    kept_suites: usize,
+    tests: usize,
+    kept_tests: usize,
+}
+
+/// Cut the tests not selected by `filter`, and the suites left without tests,
+/// out of an output.xml.
+///
+/// The root suite is always kept. If anything is cut, the ids of the
+/// remaining suites and tests are renumbered and the statistics written anew.
+pub(crate) fn filter_output_xml(xml: &str, filter: &Filter) -> Result<String, ParseError> {
+    let result = robot::parse_output_xml(xml)?;
+    let root = Selection::new(filter, &result.suite, &mut Vec::new());
+    if root.keeps_all() {
+        return Ok(xml.to_string());
+    }
+    let mut statistics = String::new();
+    robot::write_statistics(&mut statistics, &filter.apply(result));
+    let mut output = String::with_capacity(xml.len());
+    // `xml` is copied to `output` up to here
+    let mut copied = 0;
+    let mut replace = |output: &mut String, start: usize, end: usize, with: &str| {
+        output.push_str(&xml[copied..start]);
+        output.push_str(with);
+        copied = end;
+    };
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut reader = Reader::from_str(xml);
+    loop {
+        let start = reader.buffer_position() as usize;
+        let (element, empty) = match reader.read_event()? {
+            Event::Start(element) => (element, false),
+            Event::Empty(element) => (element, true),
+            Event::End(element) if element.name().as_ref() == b"suite" => {
+                stack.pop();
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        let keep = match (element.name().as_ref(), stack.last_mut()) {
+            (b"suite", None) => Some((&root, String::from("s1"))),
+            (b"suite", Some(parent)) => {
+                let selection = &parent.selection.suites[parent.suites];
+                parent.suites += 1;
+                selection.keep.then(|| {
+                    parent.kept_suites += 1;
+                    (selection, format!("{}-s{}", parent.id, parent.kept_suites))
+                })
+            }
+            (b"test", Some(suite)) => {
+                let keep = suite.selection.tests[suite.tests];
+                suite.tests += 1;
+                keep.then(|| {
+                    suite.kept_tests += 1;
+                    (
+                        suite.selection,
+                        format!("{}-t{}", suite.id, suite.kept_tests),
+                    )
+                })
+            }
+            (b"statistics", None) => {
+                let end = with_line_break(xml, end_of(&mut reader, &element, empty)?);
+                replace(&mut output, start, end, &statistics);
+                continue;
+            }
+            _ => continue,
+        };
+        match keep {
+            Some((selection, id)) => {
+                if let Some(tag) = with_id(&element, &id, empty)? {
+                    replace(&mut output, start, reader.buffer_position() as usize, &tag);
+                }
+                if element.name().as_ref() == b"suite" && !empty {
+                    stack.push(Frame {
+                        selection,
+                        id,
+                        suites: 0,
+                        kept_suites: 0,
+                        tests: 0,
+                        kept_tests: 0,
+                    });
+                }
+            }
+            None => {
+                let end = with_line_break(xml, end_of(&mut reader, &element, empty)?);
+                replace(&mut output, start, end, "");
+            }
+        }
+    }
+    output.push_str(&xml[copied..]);
+    Ok(output)
+}
+
+/// Skip to the end of `element` and return the position after it.
+fn end_of(
+    reader: &mut Reader<&[u8]>,
+    element: &BytesStart,
+    empty: bool,
+) -> Result<usize, ParseError> {
+    if !empty {
+        reader.read_to_end(element.name())?;
+    }
+    Ok(reader.buffer_position() as usize)
+}
+
+/// `end` moved past a line break following it, which goes with the element
+/// before it.
+fn with_line_break(xml: &str, end: usize) -> usize {
+    if xml[end..].starts_with("\r\n") {
+        end + 2
+    } else if xml[end..].starts_with('\n') {
+        end + 1
+    } else {
+        end
+    }
+}
+
+/// The start tag of `element` with its id attribute set to `id`, or `None` if
+/// it has no other id.
+fn with_id(element: &BytesStart, id: &str, empty: bool) -> Result<Option<String>, ParseError> {
+    let mut tag = format!("<{}", String::from_utf8_lossy(element.name().as_ref()));
+    let mut changed = false;
+    for attribute in element.attributes() {
+        let attribute = attribute?;
+        let key = String::from_utf8_lossy(attribute.key.as_ref()).into_owned();
+        let mut value = attribute.unescape_value()?.into_owned();
+        if key == "id" && value != id {
+            value = id.to_string();
+            changed = true;
+        }
+        tag.push_str(&format!(" {key}=\"{}\"", escape(&value)));
+    }
+    tag.push_str(if empty { "/>" } else { ">" });
+    Ok(changed.then_some(tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<robot generator="Robot 7.0.1" generated="2024-05-03T08:15:03.512345" rpa="false" schemaversion="5">
+<suite id="s1" name="Tests" source="/work/tests">
+<suite id="s1-s1" name="Login" source="/work/tests/login.robot">
+<kw name="Open Browser" owner="SeleniumLibrary" type="SETUP">
+<msg time="2024-05-03T08:15:00.011000" level="INFO">Opening browser 'chrome'.</msg>
+<arg>https://shop.example.com</arg>
+<status status="PASS" start="2024-05-03T08:15:00.010000" elapsed="0.9"/>
+</kw>
+<test id="s1-s1-t1" name="Valid Login" line="8">
+<kw name="Input Text" owner="SeleniumLibrary">
+<arg>id=user</arg>
+<status status="PASS" start="2024-05-03T08:15:01.080000" elapsed="0.2"/>
+</kw>
+<tag>smoke</tag>
+<status status="PASS" start="2024-05-03T08:15:00.915000" elapsed="0.61"/>
+</test>
+<test id="s1-s1-t2" name="Invalid Login" line="13">
+<doc>Wrong password &amp; user.</doc>
+<tag>negative</tag>
+<status status="PASS" start="2024-05-03T08:15:01.525000" elapsed="0.325"/>
+</test>
+<doc>Login with valid and invalid credentials.</doc>
+<meta name="Owner">shop-team</meta>
+<status status="PASS" start="2024-05-03T08:15:00.005000" elapsed="1.91"/>
+</suite>
+<suite id="s1-s2" name="Cart" source="/work/tests/cart.robot">
+<test id="s1-s2-t1" name="Add Item" line="5">
+<tag>negative</tag>
+<status status="FAIL" start="2024-05-03T08:15:01.915000" elapsed="5.02">Not visible.</status>
+</test>
+<status status="FAIL" start="2024-05-03T08:15:01.910000" elapsed="5.03"/>
+</suite>
+<status status="FAIL" start="2024-05-03T08:15:00.000000" elapsed="6.95"/>
+</suite>
+<statistics>
+<total>
+<stat pass="2" fail="1" skip="0">All Tests</stat>
+</total>
+</statistics>
+<errors>
+</errors>
+</robot>
+"#;
+
+    fn filter(include_tags: &[&str], exclude_tests: &[&str]) -> Filter {
+        Filter {
+            include_tags: include_tags.iter().map(|tag| tag.to_string()).collect(),
+            exclude_tests: exclude_tests.iter().map(|test| test.to_string()).collect(),
+            ..Filter::default()
+        }
+    }
+
+    #[test]
+    fn keeping_all_tests_leaves_output_as_it_is() {
+        let filtered = filter_output_xml(OUTPUT, &filter(&["smoke", "negative"], &[])).unwrap();
+        assert_eq!(filtered, OUTPUT);
+    }
+
+    #[test]
+    fn cuts_tests_and_empty_suites() {
+        let filtered = filter_output_xml(OUTPUT, &filter(&["smoke"], &[])).unwrap();
+        assert!(filtered.contains("<test id=\"s1-s1-t1\" name=\"Valid Login\" line=\"8\">"));
+        assert!(!filtered.contains("Invalid Login"));
+        assert!(!filtered.contains("Wrong password"));
+        assert!(!filtered.contains("Cart"));
+        assert!(!filtered.contains("Add Item"));
+        // what the model does not read stays
+        for kept in [
+            "<suite id=\"s1\" name=\"Tests\" source=\"/work/tests\">",
+            "<msg time=\"2024-05-03T08:15:00.011000\" level=\"INFO\">Opening browser 'chrome'.</msg>",
+            "<arg>https://shop.example.com</arg>",
+            "<doc>Login with valid and invalid credentials.</doc>",
+            "<meta name=\"Owner\">shop-team</meta>",
+            "<robot generator=\"Robot 7.0.1\"",
+        ] {
+            assert!(filtered.contains(kept), "{kept}");
+        }
+        assert!(filtered.contains("<stat pass=\"1\" fail=\"0\" skip=\"0\">All Tests</stat>"));
+        assert!(filtered.ends_with("</statistics>\n<errors>\n</errors>\n</robot>\n"));
+        let result = robot::parse_output_xml(&filtered).unwrap();
+        assert_eq!(result.suite.suites.len(), 1);
+        assert_eq!(result.suite.suites[0].tests.len(), 1);
+    }
+
+    #[test]
+    fn renumbers_remaining_ids() {
+        let filtered =
+            filter_output_xml(OUTPUT, &filter(&[], &["Valid Login", "Tests.Login"])).unwrap();
+        assert!(filtered.contains("<test id=\"s1-s1-t1\" name=\"Invalid Login\" line=\"13\">"));
+        assert!(filtered
+            .contains("<suite id=\"s1-s2\" name=\"Cart\" source=\"/work/tests/cart.robot\">"));
+
+        let filtered = filter_output_xml(OUTPUT, &filter(&[], &["Tests.Login.*"])).unwrap();
+        assert!(!filtered.contains("Login"));
+        assert!(filtered
+            .contains("<suite id=\"s1-s1\" name=\"Cart\" source=\"/work/tests/cart.robot\">"));
+        assert!(filtered.contains("<test id=\"s1-s1-t1\" name=\"Add Item\" line=\"5\">"));
+    }
+
+    #[test]
+    fn keeps_root_suite_without_tests() {
+        let filtered = filter_output_xml(OUTPUT, &filter(&["nightly"], &[])).unwrap();
+        let result = robot::parse_output_xml(&filtered).unwrap();
+        assert_eq!(result.suite.name, "Tests");
+        assert!(result.tests().is_empty());
+        assert!(filtered.contains("<stat pass=\"0\" fail=\"0\" skip=\"0\">All Tests</stat>"));
+    }
+}
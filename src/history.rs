@@ -0,0 +1,201 @@
+//! Per-test outcomes of past blends
+//!
+//! Blends are only kept in memory, so every recorded blend writes the outcome
+//! of each test in each of its files to a SQLite database.
+use crate::migrations;
+use crate::robot::RobotResult;
+use axum_test_client::history::{TestOutcome, TrendPoint};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Failure of the database or of the thread running the query
+pub(crate) type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Schema changes in order, see [`migrations::apply`].
+const MIGRATIONS: &[&str] = &["
+CREATE TABLE blends (
+    id INTEGER PRIMARY KEY,
+    project TEXT NOT NULL,
+    branch TEXT NOT NULL,
+    timestamp TEXT NOT NULL
+);
+CREATE TABLE outcomes (
+    blend INTEGER NOT NULL REFERENCES blends(id),
+    run TEXT NOT NULL,
+    test TEXT NOT NULL,
+    status TEXT NOT NULL,
+    message TEXT NOT NULL
+);
+CREATE INDEX outcomes_test ON outcomes(test);
+"];
+
+/// Labels a blend is recorded with
+pub(crate) struct Labels {
+    pub project: String,
+    pub branch: String,
+    /// Time of the runs, stored as RFC 3339 in UTC so it sorts as text.
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Handle to the history database, queries run in a blocking thread
+#[derive(Clone)]
+pub(crate) struct History {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl History {
+    pub(crate) fn open<P: AsRef<Path>>(path: P) -> rusqlite::Result<Self> {
+        let mut connection = Connection::open(path)?;
+        migrations::apply(&mut connection, MIGRATIONS)?;
+        Ok(History {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    async fn run<T: Send + 'static>(
+        &self,
+        query: impl FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    ) -> Result<T, Error> {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection.lock().map_err(|_| "history database poisoned")?;
+            Ok(query(&mut connection)?)
+        })
+        .await?
+    }
+
+    /// Store the outcomes of all tests in the blended files, returning the blend id.
+    pub(crate) async fn record(
+        &self,
+        labels: Labels,
+        files: Vec<String>,
+        results: Vec<RobotResult>,
+    ) -> Result<i64, Error> {
+        self.run(move |connection| record(connection, &labels, &files, &results))
+            .await
+    }
+
+    /// Outcomes of a test in a project, oldest first.
+    pub(crate) async fn test_history(
+        &self,
+        project: String,
+        branch: Option<String>,
+        test: String,
+    ) -> Result<Vec<TestOutcome>, Error> {
+        self.run(move |connection| test_history(connection, &project, branch.as_deref(), &test))
+            .await
+    }
+
+    /// Outcome counts per blend of a project, oldest first. Restricted to one test when given.
+    pub(crate) async fn trends(
+        &self,
+        project: String,
+        branch: Option<String>,
+        test: Option<String>,
+    ) -> Result<Vec<TrendPoint>, Error> {
+        self.run(move |connection| trends(connection, &project, branch.as_deref(), test.as_deref()))
+            .await
+    }
+}
+
+fn record(
+    connection: &mut Connection,
+    labels: &Labels,
+    files: &[String],
+    results: &[RobotResult],
+) -> rusqlite::Result<i64> {
+    let transaction = connection.transaction()?;
+    transaction.execute(
+        "INSERT INTO blends (project, branch, timestamp) VALUES (?1, ?2, ?3)",
+        params![
+            labels.project,
+            labels.branch,
+            labels.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)
+        ],
+    )?;
+    let blend = transaction.last_insert_rowid();
+    {
+        let mut insert = transaction.prepare(
+            "INSERT INTO outcomes (blend, run, test, status, message)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for (run, result) in files.iter().zip(results) {
+            for test in result.tests() {
+                insert.execute(params![
+                    blend,
+                    run,
+                    test.long_name(),
+                    test.test.status.as_str(),
+                    test.test.message
+                ])?;
+            }
+        }
+    }
+    transaction.commit()?;
+    Ok(blend)
+}
+
+fn test_history(
+    connection: &Connection,
+    project: &str,
+    branch: Option<&str>,
+    test: &str,
+) -> rusqlite::Result<Vec<TestOutcome>> {
+    let mut select = connection.prepare(
+        "SELECT blends.id, blends.timestamp, blends.branch, run, status, message
+         FROM outcomes JOIN blends ON outcomes.blend = blends.id
+         WHERE blends.project = ?1 AND (?2 IS NULL OR blends.branch = ?2) AND test = ?3
+         ORDER BY blends.timestamp, blends.id, outcomes.rowid",
+    )?;
+    let rows = select.query_map(params![project, branch, test], |row| {
+        Ok(TestOutcome {
+            blend: row.get(0)?,
+            timestamp: row.get(1)?,
+            branch: row.get(2)?,
+            run: row.get(3)?,
+            status: row.get::<_, String>(4)?.parse().map_err(|error| {
+                rusqlite::Error::FromSqlConversionFailure(4, Type::Text, Box::new(error))
+            })?,
+            message: row.get(5)?,
+        })
+    })?;
+    rows.collect()
+}
+
+fn trends(
+    connection: &Connection,
+    project: &str,
+    branch: Option<&str>,
+    test: Option<&str>,
+) -> rusqlite::Result<Vec<TrendPoint>> {
+    let mut select = connection.prepare(
+        "SELECT blends.id, blends.timestamp, blends.branch, COUNT(outcomes.test),
+             COUNT(CASE WHEN outcomes.status = 'PASS' THEN 1 END),
+             COUNT(CASE WHEN outcomes.status = 'FAIL' THEN 1 END)
+         FROM blends JOIN outcomes ON outcomes.blend = blends.id
+         WHERE blends.project = ?1 AND (?2 IS NULL OR blends.branch = ?2)
+             AND (?3 IS NULL OR outcomes.test = ?3)
+         GROUP BY blends.id
+         ORDER BY blends.timestamp, blends.id",
+    )?;
+    let rows = select.query_map(params![project, branch, test], |row| {
+        let passed: usize = row.get(4)?;
+        let failed: usize = row.get(5)?;
+        Ok(TrendPoint {
+            blend: row.get(0)?,
+            timestamp: row.get(1)?,
+            branch: row.get(2)?,
+            total: row.get(3)?,
+            passed,
+            failed,
+            pass_rate: match passed + failed {
+                0 => None,
+                decided => Some(passed as f64 / decided as f64),
+            },
+        })
+    })?;
+    rows.collect()
+}
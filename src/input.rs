@@ -0,0 +1,52 @@
+//! Format detection for uploaded results
+//!
+//! `blend_result` only reads Robot Framework output.xml, so other formats are
+//! converted to it when they are uploaded.
+use crate::junit;
+use crate::robot::{self, ParseError};
+use crate::robot_json;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+/// Format of an uploaded result
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Format {
+    RobotXml,
+    /// output.json of Robot Framework 7.
+    RobotJson,
+    JUnit,
+    Unknown,
+}
+
+/// Guess the format from the root element, or a leading `{` for json.
+pub(crate) fn detect(data: &str) -> Format {
+    if data.trim_start().starts_with('{') {
+        return Format::RobotJson;
+    }
+    let mut reader = Reader::from_str(data);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(start)) | Ok(Event::Empty(start)) => {
+                return match start.name().as_ref() {
+                    b"robot" => Format::RobotXml,
+                    b"testsuites" | b"testsuite" | b"assemblies" | b"assembly" => Format::JUnit,
+                    _ => Format::Unknown,
+                }
+            }
+            Ok(Event::Eof) | Err(_) => return Format::Unknown,
+            Ok(_) => (),
+        }
+    }
+}
+
+/// Convert an upload to output.xml. Robot results are kept as they are, unknown formats rejected.
+pub(crate) fn normalize(data: String) -> Result<String, ParseError> {
+    match detect(&data) {
+        Format::RobotJson => Ok(robot::to_output_xml(&robot_json::parse_output_json(&data)?)),
+        Format::JUnit => Ok(robot::to_output_xml(&junit::parse_junit(&data)?)),
+        Format::RobotXml => Ok(data),
+        Format::Unknown => Err(ParseError(String::from(
+            "unknown format, expected Robot Framework xml or json, JUnit or xUnit xml",
+        ))),
+    }
+}
@@ -0,0 +1,185 @@
+//! JUnit and xUnit results read into the Robot Framework result model
+//!
+//! Understands the JUnit XML written by pytest, cargo-nextest and most CI tools
+//! (`testsuites`/`testsuite`/`testcase`) and the xUnit.net v2 format
+//! (`assemblies`/`assembly`/`collection`/`test`).
+use crate::robot::{
+    format_iso_time, parse_document, parse_elapsed, Element, ParseError, RobotResult, Status,
+    Suite, Test,
+};
+use chrono::{DateTime, NaiveDateTime};
+
+/// Parse a JUnit or xUnit.net document.
+pub fn parse_junit(xml: &str) -> Result<RobotResult, ParseError> {
+    let root = parse_document(xml)?;
+    let suite = match root.name.as_str() {
+        "testsuites" => {
+            let mut suites: Vec<Suite> = root
+                .children("testsuite")
+                .map(read_testsuite)
+                .collect::<Result<_, _>>()?;
+            // pytest wraps its only testsuite, keep long names short
+            match suites.len() {
+                1 => suites.remove(0),
+                _ => Suite {
+                    name: root.attribute("name").unwrap_or("JUnit").to_string(),
+                    suites,
+                    tests: Vec::new(),
+                    keywords: Vec::new(),
+                    message: String::new(),
+                    start: None,
+                    elapsed: None,
+                },
+            }
+        }
+        "testsuite" => read_testsuite(&root)?,
+        "assemblies" => Suite {
+            name: String::from("xUnit"),
+            suites: root
+                .children("assembly")
+                .map(read_xunit_suite)
+                .collect::<Result<_, _>>()?,
+            tests: Vec::new(),
+            keywords: Vec::new(),
+            message: String::new(),
+            start: None,
+            elapsed: None,
+        },
+        "assembly" => read_xunit_suite(&root)?,
+        other => return Err(ParseError(format!("unexpected root element: {other}"))),
+    };
+    Ok(RobotResult { suite })
+}
+
+/// A testsuite, its testcases grouped into child suites by classname.
+fn read_testsuite(element: &Element) -> Result<Suite, ParseError> {
+    let name = element.attribute("name").unwrap_or_default().to_string();
+    let mut suite = Suite {
+        suites: element
+            .children("testsuite")
+            .map(read_testsuite)
+            .collect::<Result<_, _>>()?,
+        tests: Vec::new(),
+        keywords: Vec::new(),
+        message: String::new(),
+        start: element.attribute("timestamp").and_then(junit_start),
+        elapsed: read_time(element)?,
+        name,
+    };
+    for testcase in element.children("testcase") {
+        let test = read_testcase(testcase)?;
+        match testcase.attribute("classname") {
+            Some(class) if !class.is_empty() && class != suite.name => {
+                match suite.suites.iter_mut().find(|child| child.name == class) {
+                    Some(child) => child.tests.push(test),
+                    None => suite.suites.push(Suite {
+                        name: class.to_string(),
+                        suites: Vec::new(),
+                        tests: vec![test],
+                        keywords: Vec::new(),
+                        message: String::new(),
+                        start: None,
+                        elapsed: None,
+                    }),
+                }
+            }
+            _ => suite.tests.push(test),
+        }
+    }
+    Ok(suite)
+}
+
+fn read_testcase(element: &Element) -> Result<Test, ParseError> {
+    let outcome = ["failure", "error"]
+        .into_iter()
+        .find_map(|name| element.child(name))
+        .map(|failure| (Status::Fail, failure))
+        .or_else(|| {
+            element
+                .child("skipped")
+                .map(|skipped| (Status::Skip, skipped))
+        });
+    let (status, message) = match outcome {
+        Some((status, detail)) => (status, detail_message(detail)),
+        None => (Status::Pass, String::new()),
+    };
+    Ok(Test {
+        name: element.attribute("name").unwrap_or_default().to_string(),
+        status,
+        message,
+        elapsed: read_time(element)?,
+        tags: Vec::new(),
+        keywords: Vec::new(),
+        start: None,
+    })
+}
+
+/// Seconds in the `time` attribute, if there is one.
+fn read_time(element: &Element) -> Result<Option<f64>, ParseError> {
+    element.attribute("time").map(parse_elapsed).transpose()
+}
+
+/// A testsuite `timestamp` as ISO 8601 local time, time zones are dropped.
+fn junit_start(timestamp: &str) -> Option<String> {
+    let start = match DateTime::parse_from_rfc3339(timestamp) {
+        Ok(start) => start.naive_local(),
+        Err(_) => NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S%.f").ok()?,
+    };
+    Some(format_iso_time(start))
+}
+
+/// The `message` attribute, or the element text when there is none.
+fn detail_message(element: &Element) -> String {
+    match element.attribute("message") {
+        Some(message) if !message.is_empty() => message.to_string(),
+        _ => element.text.trim().to_string(),
+    }
+}
+
+/// An xUnit.net assembly or collection with its tests.
+fn read_xunit_suite(element: &Element) -> Result<Suite, ParseError> {
+    Ok(Suite {
+        name: element.attribute("name").unwrap_or_default().to_string(),
+        suites: element
+            .children("collection")
+            .map(read_xunit_suite)
+            .collect::<Result<_, _>>()?,
+        tests: element
+            .children("test")
+            .map(read_xunit_test)
+            .collect::<Result<_, _>>()?,
+        keywords: Vec::new(),
+        message: String::new(),
+        start: None,
+        elapsed: read_time(element)?,
+    })
+}
+
+fn read_xunit_test(element: &Element) -> Result<Test, ParseError> {
+    let status = match element.attribute("result").unwrap_or_default() {
+        "Pass" => Status::Pass,
+        "Fail" => Status::Fail,
+        "Skip" => Status::Skip,
+        "NotRun" => Status::NotRun,
+        other => return Err(ParseError(format!("unknown result: {other}"))),
+    };
+    let message = match status {
+        Status::Fail => element
+            .child("failure")
+            .and_then(|failure| failure.child("message"))
+            .map(|message| message.text.trim().to_string()),
+        Status::Skip => element
+            .child("reason")
+            .map(|reason| reason.text.trim().to_string()),
+        _ => None,
+    };
+    Ok(Test {
+        name: element.attribute("name").unwrap_or_default().to_string(),
+        status,
+        message: message.unwrap_or_default(),
+        elapsed: read_time(element)?,
+        tags: Vec::new(),
+        keywords: Vec::new(),
+        start: None,
+    })
+}
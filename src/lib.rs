@@ -0,0 +1,238 @@
+//! Blend, todo and stuff api as embeddable axum routers
+//!
+//! [`ApiBuilder`] assembles all apis the way the `axum-test-server` binary serves
+//! them. The routers of the single apis, [`blend_api::router`],
+//! [`todo_api::router`] and [`stuff_api::router`], can be nested on their own.
+//! [`docs::Docs`] serves the OpenAPI document with the documentation UIs.
+//!
+//! ```no_run
+//! use axum_test_server::{blend_api::BlendState, ApiBuilder, Auth};
+//!
+//! let (router, openapi) = ApiBuilder::new()
+//!     .prefix("/results")
+//!     .blend_state(BlendState::open(":memory:").unwrap())
+//!     .auth(Auth::Disabled)
+//!     .build()
+//!     .unwrap()
+//!     .split_for_parts();
+//! # let _: axum::Router = router;
+//! ```
+use axum::extract::FromRef;
+use std::fmt;
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
+    Modify, OpenApi,
+};
+use utoipa_axum::router::OpenApiRouter;
+
+mod analysis;
+mod auth;
+pub mod blend_api;
+pub mod docs;
+mod export;
+mod filter;
+mod history;
+mod input;
+mod junit;
+mod merge;
+mod migrations;
+mod ods;
+mod report;
+mod robot;
+mod robot_json;
+pub mod stuff_api;
+pub mod todo_api;
+pub mod todo_store;
+
+pub use auth::{Auth, API_KEY_HEADER};
+
+const TODO_TAG: &str = "todo";
+
+/// Failure to open the history or todo database
+#[derive(Debug)]
+pub struct DatabaseError(rusqlite::Error);
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot open database: {}", self.0)
+    }
+}
+
+impl std::error::Error for DatabaseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+impl From<rusqlite::Error> for DatabaseError {
+    fn from(error: rusqlite::Error) -> Self {
+        DatabaseError(error)
+    }
+}
+
+/// State of the blend and todo routers, handlers pick the parts they need
+#[derive(Clone)]
+struct RouterState<S> {
+    store: S,
+    auth: Auth,
+}
+
+impl<S> FromRef<RouterState<S>> for Auth {
+    fn from_ref(state: &RouterState<S>) -> Self {
+        state.auth.clone()
+    }
+}
+
+impl FromRef<RouterState<blend_api::BlendState>> for blend_api::BlendState {
+    fn from_ref(state: &RouterState<blend_api::BlendState>) -> Self {
+        state.store.clone()
+    }
+}
+
+impl FromRef<RouterState<todo_api::TodoState>> for todo_api::TodoState {
+    fn from_ref(state: &RouterState<todo_api::TodoState>) -> Self {
+        state.store.clone()
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    modifiers(&SecurityAddon),
+    tags(
+        (name = "blend", description = "Robotframework result blender"),
+        (name = "stuff", description = "Various tests"),
+        (name = TODO_TAG, description = "Todo items management API"),
+    )
+)]
+struct ApiDoc;
+
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "api_key",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(API_KEY_HEADER))),
+            )
+        }
+    }
+}
+
+/// Builder of the router serving all apis below one prefix
+pub struct ApiBuilder {
+    prefix: String,
+    blend_state: Option<blend_api::BlendState>,
+    todo_state: Option<todo_api::TodoState>,
+    blend_auth: Auth,
+    todo_auth: Auth,
+    stuff_auth: Auth,
+}
+
+impl Default for ApiBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ApiBuilder {
+    /// The apis below `/api/v1`. The blend api expects the key in `API_KEY`,
+    /// todo and stuff expect `rocks`.
+    pub fn new() -> Self {
+        ApiBuilder {
+            prefix: String::from("/api/v1"),
+            blend_state: None,
+            todo_state: None,
+            blend_auth: Auth::Env(String::from("API_KEY")),
+            todo_auth: Auth::ApiKey(String::from("rocks")),
+            stuff_auth: Auth::ApiKey(String::from("rocks")),
+        }
+    }
+
+    /// Path the apis are nested below, e.g. `/api/v1/blend`.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Blend state, by default the history database is opened as in [`blend_api::BlendState::from_env`].
+    pub fn blend_state(mut self, state: blend_api::BlendState) -> Self {
+        self.blend_state = Some(state);
+        self
+    }
+
+    /// Todo state, by default the todo database is opened as in [`todo_api::TodoState::from_env`].
+    pub fn todo_state(mut self, state: todo_api::TodoState) -> Self {
+        self.todo_state = Some(state);
+        self
+    }
+
+    /// Use `auth` for all apis.
+    pub fn auth(self, auth: Auth) -> Self {
+        ApiBuilder {
+            blend_auth: auth.clone(),
+            todo_auth: auth.clone(),
+            stuff_auth: auth,
+            ..self
+        }
+    }
+
+    /// Check of the blend api, the key in `API_KEY` by default. Every request needs the key.
+    pub fn blend_auth(mut self, auth: Auth) -> Self {
+        self.blend_auth = auth;
+        self
+    }
+
+    /// Check of the todo api, `rocks` by default. Deleting needs the key, replacing and
+    /// updating only reject a wrong one, reading and creating are open.
+    pub fn todo_auth(mut self, auth: Auth) -> Self {
+        self.todo_auth = auth;
+        self
+    }
+
+    /// Check of the stuff api, `rocks` by default. Every request needs the key.
+    pub fn stuff_auth(mut self, auth: Auth) -> Self {
+        self.stuff_auth = auth;
+        self
+    }
+
+    /// Router with the OpenAPI document of all apis, fails if the history or todo database cannot be opened.
+    pub fn build(self) -> Result<OpenApiRouter, DatabaseError> {
+        let blend_state = match self.blend_state {
+            Some(state) => state,
+            None => blend_api::BlendState::from_env()?,
+        };
+        let todo_state = match self.todo_state {
+            Some(state) => state,
+            None => todo_api::TodoState::from_env()?,
+        };
+        let prefix = self.prefix.trim_end_matches('/');
+        Ok(OpenApiRouter::with_openapi(ApiDoc::openapi())
+            .nest(
+                &format!("{prefix}/stuff"),
+                stuff_api::router(self.stuff_auth),
+            )
+            .nest(
+                &format!("{prefix}/blend"),
+                blend_api::router(blend_state, self.blend_auth),
+            )
+            .nest(
+                &format!("{prefix}/todo"),
+                todo_api::router(todo_state, self.todo_auth),
+            ))
+    }
+}
+
+/// OpenAPI document served at `/api-docs/openapi.json` by the binary.
+///
+/// The routers are built on in-memory state, no database file is touched.
+pub fn openapi() -> utoipa::openapi::OpenApi {
+    let state = blend_api::BlendState::open(":memory:").expect("in-memory history database");
+    let (_, openapi) = ApiBuilder::new()
+        .blend_state(state)
+        .todo_state(todo_api::TodoState::new())
+        .build()
+        .expect("router with in-memory history")
+        .split_for_parts();
+    openapi
+}
@@ -1,26 +1,36 @@
 use std::net::{Ipv4Addr, SocketAddr};
 
-use std::io::Error;
-use utoipa::{
-    openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
-    Modify, OpenApi,
-};
-use utoipa_axum::router::OpenApiRouter;
-//use utoipa_rapidoc::RapiDoc;
-//use utoipa_redoc::{Redoc, Servable};
-//use utoipa_scalar::{Scalar, Servable as ScalarServable};
 use axum_server::tls_openssl::OpenSSLConfig;
+use axum_test_server::{docs::Docs, ApiBuilder};
+use std::io::Error;
 use tracing_subscriber::EnvFilter;
-use utoipa_swagger_ui::SwaggerUi;
 
-mod blend_api;
-mod stuff_api;
-mod todo_api;
-
-const TODO_TAG: &str = "todo";
+/// Write the OpenAPI document to `path`, or stdout without one.
+fn export_openapi(path: Option<String>) -> Result<(), Error> {
+    let json = axum_test_server::openapi()
+        .to_pretty_json()
+        .map_err(Error::other)?;
+    match path {
+        Some(path) => std::fs::write(path, json + "\n"),
+        None => {
+            println!("{json}");
+            Ok(())
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        None => (),
+        Some("openapi") => return export_openapi(args.next()),
+        Some(command) => {
+            eprintln!("unknown command {command}, usage: axum-test-server [openapi [FILE]]");
+            std::process::exit(2);
+        }
+    }
+
     tracing_subscriber::fmt()
         // This allows you to use, e.g., `RUST_LOG=info` or `RUST_LOG=debug`
         .with_env_filter(
@@ -31,45 +41,12 @@ async fn main() -> Result<(), Error> {
         )
         .init();
 
-    #[derive(OpenApi)]
-    #[openapi(
-        modifiers(&SecurityAddon),
-        tags(
-            (name = "blend", description = "Robotframework result blender"),
-            (name = "stuff", description = "Various tests"),
-            (name = TODO_TAG, description = "Todo items management API"),
-        )
-    )]
-    struct ApiDoc;
-
-    struct SecurityAddon;
-
-    impl Modify for SecurityAddon {
-        fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
-            if let Some(components) = openapi.components.as_mut() {
-                components.add_security_scheme(
-                    "api_key",
-                    SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("theapikey"))),
-                )
-            }
-        }
-    }
-
-    let (router, api) = OpenApiRouter::with_openapi(ApiDoc::openapi())
-        .nest("/api/v1/stuff", stuff_api::router())
-        .nest("/api/v1/blend", blend_api::router())
-        .nest("/api/v1/todo", todo_api::router())
+    let (router, api) = ApiBuilder::new()
+        .build()
+        .expect("cannot open history or todo database")
         .split_for_parts();
 
-    let router =
-        router.merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", api.clone()));
-    // .merge(Redoc::with_url("/redoc", api.clone()))
-    // There is no need to create `RapiDoc::with_openapi` because the OpenApi is served
-    // via SwaggerUi instead we only make rapidoc to point to the existing doc.
-    // .merge(RapiDoc::new("/api-docs/openapi.json").path("/rapidoc"))
-    // Alternative to above
-    // .merge(RapiDoc::with_openapi("/api-docs/openapi2.json", api).path("/rapidoc"))
-    //.merge(Scalar::with_url("/scalar", api));
+    let router = router.merge(Docs::from_env().router(api));
 
     //let address = SocketAddr::from((Ipv4Addr::UNSPECIFIED, 8080));
     let address = SocketAddr::from((Ipv4Addr::UNSPECIFIED, 44001));
@@ -0,0 +1,109 @@
+//! Merge of reruns into one result, like `rebot --merge`
+//!
+//! Suites are matched by name. A test found again in a later run replaces the
+//! earlier one unless it was skipped, tests and suites only in later runs are
+//! added. Results with different root suites are not merged.
+use crate::robot::{RobotResult, Status, Suite, Test};
+use std::fmt;
+
+/// Results whose root suites differ, which rebot refuses to merge
+#[derive(Debug)]
+pub(crate) struct DifferentRoots {
+    original: String,
+    merged: String,
+}
+
+impl fmt::Display for DifferentRoots {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cannot merge outputs containing different root suites, \
+             original suite is '{}' and merged is '{}'",
+            self.original, self.merged
+        )
+    }
+}
+
+/// Merge results in the given order, `None` when there is nothing to merge.
+pub(crate) fn merge(results: Vec<RobotResult>) -> Result<Option<RobotResult>, DifferentRoots> {
+    let mut results = results.into_iter();
+    let Some(mut merged) = results.next() else {
+        return Ok(None);
+    };
+    for result in results {
+        if result.suite.name != merged.suite.name {
+            return Err(DifferentRoots {
+                original: merged.suite.name,
+                merged: result.suite.name,
+            });
+        }
+        merge_suite(&mut merged.suite, result.suite);
+    }
+    Ok(Some(merged))
+}
+
+fn merge_suite(into: &mut Suite, from: Suite) {
+    for suite in from.suites {
+        match into
+            .suites
+            .iter_mut()
+            .find(|child| child.name == suite.name)
+        {
+            Some(child) => merge_suite(child, suite),
+            None => into.suites.push(Suite {
+                message: added_message("Suite", &suite.message),
+                ..suite
+            }),
+        }
+    }
+    for test in from.tests {
+        match into.tests.iter_mut().find(|old| old.name == test.name) {
+            Some(old) if test.status == Status::Skip => old.message = skipped_message(old, &test),
+            Some(old) => *old = merged_test(old, test),
+            None => into.tests.push(Test {
+                message: added_message("Test", &test.message),
+                ..test
+            }),
+        }
+    }
+    // setup and teardown of the later run, even if it has none
+    into.keywords = from.keywords;
+    if from.elapsed.is_some() {
+        into.elapsed = from.elapsed;
+    }
+}
+
+/// The rerun test, its message telling what it replaced.
+fn merged_test(old: &Test, new: Test) -> Test {
+    let message = format!(
+        "Re-executed test has been merged.\n\n\
+         New status: {}\nNew message: {}\n\n\
+         Old status: {}\nOld message: {}",
+        new.status.as_str(),
+        new.message,
+        old.status.as_str(),
+        old.message
+    );
+    Test { message, ..new }
+}
+
+/// Message of the earlier test when its rerun was skipped, the rerun itself is dropped.
+fn skipped_message(old: &Test, new: &Test) -> String {
+    let mut message = format!(
+        "Re-executed test has been merged. \
+         Latter result had SKIP status and was ignored. Message:\n{}",
+        new.message
+    );
+    if !old.message.is_empty() {
+        message.push_str(&format!("\n\nOriginal message:\n{}", old.message));
+    }
+    message
+}
+
+/// Message of a test or suite only found in a later run.
+fn added_message(kind: &str, message: &str) -> String {
+    match message {
+        "" => format!("{kind} added from merged output."),
+        message => format!("{kind} added from merged output.\n\n{message}"),
+    }
+}
@@ -0,0 +1,19 @@
+//! Schema versions of the SQLite databases
+use rusqlite::Connection;
+
+/// Apply the schema changes in `migrations` that are not applied yet.
+///
+/// The number of applied changes is kept in `PRAGMA user_version`, so
+/// `migrations` may only ever be appended to.
+pub(crate) fn apply(connection: &mut Connection, migrations: &[&str]) -> rusqlite::Result<()> {
+    let transaction = connection.transaction()?;
+    let version: usize =
+        transaction.query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))? as usize;
+    if version < migrations.len() {
+        for migration in &migrations[version..] {
+            transaction.execute_batch(migration)?;
+        }
+        transaction.pragma_update(None, "user_version", migrations.len() as i64)?;
+    }
+    transaction.commit()
+}
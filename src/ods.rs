@@ -0,0 +1,19 @@
+use spreadsheet_ods::{OdsError, Sheet, Value};
+
+/// Append sheets to the spreadsheet exported by `blend_result`.
+pub(crate) fn append_sheets(
+    ods: &[u8],
+    sheets: Vec<(&str, Vec<Vec<Value>>)>,
+) -> Result<Vec<u8>, OdsError> {
+    let mut book = spreadsheet_ods::read_ods_buf(ods)?;
+    for (name, rows) in sheets {
+        let mut sheet = Sheet::new(name);
+        for (row, values) in rows.into_iter().enumerate() {
+            for (col, value) in values.into_iter().enumerate() {
+                sheet.set_value(row as u32, col as u32, value);
+            }
+        }
+        book.push_sheet(sheet);
+    }
+    spreadsheet_ods::write_ods_buf(&mut book, Vec::new())
+}
@@ -0,0 +1,256 @@
+//! Server-rendered HTML reports
+use crate::robot::{RobotResult, Status, Test};
+use axum_test_client::analysis::Comparison;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+th { background: #eee; }
+.fail { color: #b00; }
+.pass { color: #070; }
+pre { margin: 0; white-space: pre-wrap; }
+td.pass { background: #dfd; }
+td.fail { background: #fdd; }
+td.skip, td.notrun { background: #ffd; }
+th.sortable { cursor: pointer; }
+.filters { margin-bottom: 1em; }";
+
+/// Sorting on header clicks and filtering of the blend table.
+const BLEND_SCRIPT: &str = "const table = document.getElementById('blend');
+const rows = () => Array.from(table.tBodies[0].rows);
+document.querySelectorAll('th.sortable').forEach((th) => {
+  th.addEventListener('click', () => {
+    const column = th.cellIndex;
+    const ascending = th.dataset.order !== 'asc';
+    th.dataset.order = ascending ? 'asc' : 'desc';
+    const sorted = rows().sort((a, b) => {
+      const order = a.cells[column].dataset.key.localeCompare(b.cells[column].dataset.key);
+      return ascending ? order : -order;
+    });
+    sorted.forEach((row) => table.tBodies[0].appendChild(row));
+  });
+});
+function filter() {
+  const suite = document.getElementById('suite').value;
+  const status = document.getElementById('status').value;
+  rows().forEach((row) => {
+    const suiteMatch = suite === '' || row.dataset.suite === suite
+      || row.dataset.suite.startsWith(suite + '.');
+    const statuses = row.dataset.statuses.split(' ');
+    const statusMatch = status === '' || (status === 'DIFFERING'
+      ? row.dataset.differing === 'true' : statuses.includes(status));
+    row.hidden = !(suiteMatch && statusMatch);
+  });
+}
+document.getElementById('suite').addEventListener('change', filter);
+document.getElementById('status').addEventListener('change', filter);";
+
+/// Escape text for use in html content and attribute values.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Complete html document around `body`.
+pub(crate) fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>\n{STYLE}\n</style>\n</head>\n<body>\n<h1>{title}</h1>\n{body}</body>\n</html>\n",
+        title = escape(title),
+    )
+}
+
+fn test_list(html: &mut String, heading: &str, class: &str, tests: &[String]) {
+    let _ = writeln!(html, "<h2>{heading} ({})</h2>", tests.len());
+    if tests.is_empty() {
+        return;
+    }
+    match class {
+        "" => html.push_str("<ul>\n"),
+        class => {
+            let _ = writeln!(html, "<ul class=\"{class}\">");
+        }
+    }
+    for test in tests {
+        let _ = writeln!(html, "<li>{}</li>", escape(test));
+    }
+    html.push_str("</ul>\n");
+}
+
+pub(crate) fn comparison(baseline: &str, current: &str, comparison: &Comparison) -> String {
+    let mut html = String::new();
+    let _ = writeln!(
+        html,
+        "<p>Baseline <b>{}</b> against current <b>{}</b></p>",
+        escape(baseline),
+        escape(current)
+    );
+    test_list(
+        &mut html,
+        "Newly failing",
+        "fail",
+        &comparison.newly_failing,
+    );
+    test_list(
+        &mut html,
+        "Newly passing",
+        "pass",
+        &comparison.newly_passing,
+    );
+    test_list(&mut html, "Added", "", &comparison.added);
+    test_list(&mut html, "Removed", "", &comparison.removed);
+
+    let _ = writeln!(
+        html,
+        "<h2>Changed messages ({})</h2>",
+        comparison.changed_messages.len()
+    );
+    if !comparison.changed_messages.is_empty() {
+        html.push_str("<table>\n<tr><th>Test</th><th>Baseline</th><th>Current</th></tr>\n");
+        for change in &comparison.changed_messages {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td><pre>{}</pre></td><td><pre>{}</pre></td></tr>",
+                escape(&change.test),
+                escape(&change.baseline),
+                escape(&change.current)
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    let _ = writeln!(
+        html,
+        "<h2>Duration regressions ({})</h2>",
+        comparison.duration_regressions.len()
+    );
+    if !comparison.duration_regressions.is_empty() {
+        html.push_str(
+            "<table>\n<tr><th>Test</th><th>Baseline [s]</th><th>Current [s]</th><th>Increase [s]</th></tr>\n",
+        );
+        for change in &comparison.duration_regressions {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{:.3}</td><td>{:.3}</td><td>{:.3}</td></tr>",
+                escape(&change.test),
+                change.baseline,
+                change.current,
+                change.current - change.baseline
+            );
+        }
+        html.push_str("</table>\n");
+    }
+    page("Run comparison", &html)
+}
+
+fn status_class(status: Status) -> &'static str {
+    match status {
+        Status::Pass => "pass",
+        Status::Fail => "fail",
+        Status::Skip => "skip",
+        Status::NotRun => "notrun",
+    }
+}
+
+/// Blended session as sortable, filterable table with one status column per file.
+/// `download` links the spreadsheet, usually as data URL.
+pub(crate) fn blend(files: &[String], results: &[RobotResult], download: Option<&str>) -> String {
+    let mut rows: Vec<(String, String, Vec<Option<&Test>>)> = Vec::new();
+    let mut index = HashMap::new();
+    for (column, result) in results.iter().enumerate() {
+        for test in result.tests() {
+            let row = *index.entry(test.long_name()).or_insert_with(|| {
+                rows.push((
+                    test.suite.clone(),
+                    test.test.name.clone(),
+                    vec![None; results.len()],
+                ));
+                rows.len() - 1
+            });
+            rows[row].2[column] = Some(test.test);
+        }
+    }
+    let suites: BTreeSet<&str> = rows.iter().map(|(suite, _, _)| suite.as_str()).collect();
+
+    let mut html = String::new();
+    html.push_str("<div class=\"filters\">\n<label>Suite <select id=\"suite\">\n<option value=\"\">All</option>\n");
+    for suite in suites {
+        let _ = writeln!(html, "<option>{}</option>", escape(suite));
+    }
+    html.push_str(
+        "</select></label>\n<label>Status <select id=\"status\">\n<option value=\"\">All</option>\n\
+         <option>PASS</option>\n<option>FAIL</option>\n<option>SKIP</option>\n\
+         <option value=\"NOT_RUN\">NOT RUN</option>\n<option value=\"DIFFERING\">Differing</option>\n</select></label>\n",
+    );
+    if let Some(download) = download {
+        let _ = writeln!(
+            html,
+            "<a href=\"{}\" download=\"blend.ods\">Download spreadsheet</a>",
+            escape(download)
+        );
+    }
+    html.push_str("</div>\n");
+
+    html.push_str("<table id=\"blend\">\n<thead>\n<tr><th class=\"sortable\">Suite</th><th class=\"sortable\">Test</th>");
+    for file in files {
+        let _ = write!(html, "<th class=\"sortable\">{}</th>", escape(file));
+    }
+    html.push_str("</tr>\n</thead>\n<tbody>\n");
+    for (suite, name, tests) in &rows {
+        let statuses: Vec<Option<Status>> =
+            tests.iter().map(|test| test.map(|t| t.status)).collect();
+        let differing = statuses.iter().any(|status| *status != statuses[0]);
+        let _ = write!(
+            html,
+            "<tr data-suite=\"{suite}\" data-statuses=\"{}\" data-differing=\"{differing}\">\
+             <td data-key=\"{suite}\">{suite}</td><td data-key=\"{name}\">{name}</td>",
+            statuses
+                .iter()
+                .flatten()
+                .map(|status| status.as_str().replace(' ', "_"))
+                .collect::<Vec<_>>()
+                .join(" "),
+            suite = escape(suite),
+            name = escape(name),
+        );
+        for test in tests {
+            match test {
+                Some(test) if test.message.is_empty() => {
+                    let _ = write!(
+                        html,
+                        "<td class=\"{}\" data-key=\"{status}\">{status}</td>",
+                        status_class(test.status),
+                        status = test.status.as_str()
+                    );
+                }
+                Some(test) => {
+                    let _ = write!(
+                        html,
+                        "<td class=\"{}\" data-key=\"{status}\"><details><summary>{status}</summary>\
+                         <pre>{}</pre></details></td>",
+                        status_class(test.status),
+                        escape(&test.message),
+                        status = test.status.as_str()
+                    );
+                }
+                None => html.push_str("<td data-key=\"\"></td>"),
+            }
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n");
+    let _ = writeln!(html, "<script>\n{BLEND_SCRIPT}\n</script>");
+    page("Blend", &html)
+}
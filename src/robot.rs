@@ -0,0 +1,525 @@
+//! Minimal Robot Framework result model
+//!
+//! `blend_result` keeps its parsed results to itself, so the analysis endpoints
+//! read the uploaded output.xml files into this model instead. The model itself
+//! lives in `axum_test_client` so clients get the same types.
+use axum_test_client::robot::UnknownStatus;
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub use axum_test_client::robot::{Keyword, RobotResult, Status, Suite, Test};
+
+#[derive(Debug)]
+pub struct ParseError(pub(crate) String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<UnknownStatus> for ParseError {
+    fn from(error: UnknownStatus) -> Self {
+        ParseError(error.to_string())
+    }
+}
+
+impl From<quick_xml::Error> for ParseError {
+    fn from(error: quick_xml::Error) -> Self {
+        ParseError(error.to_string())
+    }
+}
+
+impl From<quick_xml::events::attributes::AttrError> for ParseError {
+    fn from(error: quick_xml::events::attributes::AttrError) -> Self {
+        ParseError(error.to_string())
+    }
+}
+
+/// Generic xml element, the result file is small enough to keep in memory.
+pub(crate) struct Element {
+    pub(crate) name: String,
+    attributes: Vec<(String, String)>,
+    pub(crate) children: Vec<Element>,
+    pub(crate) text: String,
+}
+
+impl Element {
+    fn from_start(start: &BytesStart) -> Result<Self, ParseError> {
+        let mut attributes = Vec::new();
+        for attribute in start.attributes() {
+            let attribute = attribute?;
+            attributes.push((
+                String::from_utf8_lossy(attribute.key.as_ref()).into_owned(),
+                attribute.unescape_value()?.into_owned(),
+            ));
+        }
+        Ok(Element {
+            name: String::from_utf8_lossy(start.name().as_ref()).into_owned(),
+            attributes,
+            children: Vec::new(),
+            text: String::new(),
+        })
+    }
+
+    pub(crate) fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub(crate) fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    pub(crate) fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+}
+
+pub(crate) fn parse_document(xml: &str) -> Result<Element, ParseError> {
+    let mut reader = Reader::from_str(xml);
+    let mut stack: Vec<Element> = Vec::new();
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) => stack.push(Element::from_start(&start)?),
+            Event::Empty(start) => {
+                let element = Element::from_start(&start)?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::Text(text) => {
+                if let Some(current) = stack.last_mut() {
+                    current.text.push_str(&text.unescape()?);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(current) = stack.last_mut() {
+                    current
+                        .text
+                        .push_str(&String::from_utf8_lossy(&data.into_inner()));
+                }
+            }
+            Event::End(_) => {
+                let element = stack
+                    .pop()
+                    .ok_or_else(|| ParseError(String::from("unbalanced end tag")))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::Eof => return Err(ParseError(String::from("unexpected end of file"))),
+            _ => (),
+        }
+    }
+}
+
+/// Parse a Robot Framework output.xml.
+pub fn parse_output_xml(xml: &str) -> Result<RobotResult, ParseError> {
+    let root = parse_document(xml)?;
+    if root.name != "robot" {
+        return Err(ParseError(format!(
+            "unexpected root element: {}",
+            root.name
+        )));
+    }
+    let suite = root
+        .child("suite")
+        .ok_or_else(|| ParseError(String::from("missing top level suite")))?;
+    Ok(RobotResult {
+        suite: read_suite(suite)?,
+    })
+}
+
+fn read_suite(element: &Element) -> Result<Suite, ParseError> {
+    Ok(Suite {
+        name: element.attribute("name").unwrap_or_default().to_string(),
+        suites: element
+            .children("suite")
+            .map(read_suite)
+            .collect::<Result<_, _>>()?,
+        tests: element
+            .children("test")
+            .map(read_test)
+            .collect::<Result<_, _>>()?,
+        keywords: read_keywords(element)?,
+        message: element
+            .child("status")
+            .map(|status| status.text.clone())
+            .unwrap_or_default(),
+        start: element.child("status").and_then(read_start),
+        elapsed: element
+            .child("status")
+            .map(read_elapsed)
+            .transpose()?
+            .flatten(),
+    })
+}
+
+fn read_test(element: &Element) -> Result<Test, ParseError> {
+    let status = element
+        .child("status")
+        .ok_or_else(|| ParseError(String::from("test without status")))?;
+    Ok(Test {
+        name: element.attribute("name").unwrap_or_default().to_string(),
+        status: status.attribute("status").unwrap_or_default().parse()?,
+        message: status.text.clone(),
+        start: read_start(status),
+        elapsed: read_elapsed(status)?,
+        // Robot Framework before 4.0 wraps the tags in `tags`
+        tags: element
+            .children("tag")
+            .chain(
+                element
+                    .children("tags")
+                    .flat_map(|tags| tags.children("tag")),
+            )
+            .map(|tag| tag.text.clone())
+            .collect(),
+        keywords: read_keywords(element)?,
+    })
+}
+
+/// Elements holding keywords or other control structures.
+const KEYWORD_ELEMENTS: [&str; 11] = [
+    "kw", "for", "iter", "if", "branch", "try", "while", "group", "return", "break", "continue",
+];
+
+fn read_keywords(element: &Element) -> Result<Vec<Keyword>, ParseError> {
+    element
+        .children
+        .iter()
+        .filter(|child| KEYWORD_ELEMENTS.contains(&child.name.as_str()))
+        .map(read_keyword)
+        .collect()
+}
+
+fn read_keyword(element: &Element) -> Result<Keyword, ParseError> {
+    let status = element.child("status");
+    let kind = match (element.name.as_str(), element.attribute("type")) {
+        (_, Some(kind)) => kind.to_uppercase(),
+        ("kw", None) => String::from("KEYWORD"),
+        (name, None) => name.to_uppercase(),
+    };
+    Ok(Keyword {
+        name: element
+            .attribute("name")
+            .or_else(|| element.attribute("condition"))
+            .unwrap_or_default()
+            .to_string(),
+        kind,
+        library: element
+            .attribute("library")
+            .or_else(|| element.attribute("owner"))
+            .map(String::from),
+        status: match status.and_then(|status| status.attribute("status")) {
+            Some(status) => status.parse()?,
+            None => Status::NotRun,
+        },
+        start: status.and_then(read_start),
+        elapsed: status.map(read_elapsed).transpose()?.flatten(),
+        keywords: read_keywords(element)?,
+    })
+}
+
+/// Time format of `starttime` and `endtime` before Robot Framework 7.
+const OUTPUT_TIME: &str = "%Y%m%d %H:%M:%S%.3f";
+
+/// Time format of the model and of `start` in Robot Framework 7.
+const ISO_TIME: &str = "%Y-%m-%dT%H:%M:%S%.6f";
+
+pub(crate) fn format_iso_time(time: NaiveDateTime) -> String {
+    time.format(ISO_TIME).to_string()
+}
+
+/// Start time from `start` (RF 7) or `starttime` (older versions).
+fn read_start(status: &Element) -> Option<String> {
+    if let Some(start) = status.attribute("start") {
+        return Some(start.to_string());
+    }
+    let start = NaiveDateTime::parse_from_str(status.attribute("starttime")?, OUTPUT_TIME).ok()?;
+    Some(format_iso_time(start))
+}
+
+/// Elapsed seconds from `elapsed` (RF 7) or `starttime` and `endtime` (older versions).
+fn read_elapsed(status: &Element) -> Result<Option<f64>, ParseError> {
+    if let Some(elapsed) = status.attribute("elapsed") {
+        return parse_elapsed(elapsed).map(Some);
+    }
+    let time = |name| NaiveDateTime::parse_from_str(status.attribute(name)?, OUTPUT_TIME).ok();
+    let elapsed = time("endtime")
+        .zip(time("starttime"))
+        .map(|(end, start)| end - start);
+    Ok(elapsed.map(|elapsed| elapsed.num_milliseconds() as f64 / 1000.0))
+}
+
+/// Longest run time accepted, ten years in seconds.
+const MAX_ELAPSED: f64 = 10.0 * 365.0 * 24.0 * 3600.0;
+
+/// `elapsed` seconds if they are neither negative nor longer than [`MAX_ELAPSED`].
+pub(crate) fn check_elapsed(elapsed: f64) -> Result<f64, ParseError> {
+    match (0.0..=MAX_ELAPSED).contains(&elapsed) {
+        true => Ok(elapsed),
+        false => Err(ParseError(format!("invalid elapsed time: {elapsed}"))),
+    }
+}
+
+/// Seconds as written in `elapsed` or a JUnit `time`, see [`check_elapsed`].
+pub(crate) fn parse_elapsed(elapsed: &str) -> Result<f64, ParseError> {
+    let seconds = elapsed
+        .trim()
+        .parse()
+        .map_err(|_| ParseError(format!("invalid elapsed time: {elapsed}")))?;
+    check_elapsed(seconds)
+}
+
+/// Write a result as output.xml of schema version 4 (Robot Framework 6.1), the
+/// format `blend_result` reads.
+///
+/// Items without a start time, such as converted JUnit tests, are written as
+/// starting with their parent, the document with the time it is written.
+pub fn to_output_xml(result: &RobotResult) -> String {
+    let generated = Local::now().naive_local();
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <robot generator=\"axum-test-server\" generated=\"{}\" rpa=\"false\" schemaversion=\"4\">\n",
+        format_time(generated)
+    );
+    write_suite(&mut xml, &result.suite, "s1", generated);
+    write_statistics(&mut xml, result);
+    xml.push_str("<errors>\n</errors>\n</robot>\n");
+    xml
+}
+
+fn write_suite(xml: &mut String, suite: &Suite, id: &str, parent: NaiveDateTime) {
+    let start = start_time(suite.start.as_deref(), parent);
+    xml.push_str(&format!(
+        "<suite id=\"{id}\" name=\"{}\">\n",
+        escape(&suite.name)
+    ));
+    let (setup, teardown): (Vec<&Keyword>, Vec<&Keyword>) = suite
+        .keywords
+        .iter()
+        .partition(|keyword| keyword.kind == "SETUP");
+    for keyword in setup {
+        write_keyword(xml, keyword, "suite", start);
+    }
+    for (index, child) in suite.suites.iter().enumerate() {
+        write_suite(xml, child, &format!("{id}-s{}", index + 1), start);
+    }
+    for (index, test) in suite.tests.iter().enumerate() {
+        let test_start = start_time(test.start.as_deref(), start);
+        xml.push_str(&format!(
+            "<test id=\"{id}-t{}\" name=\"{}\">\n",
+            index + 1,
+            escape(&test.name)
+        ));
+        for keyword in &test.keywords {
+            write_keyword(xml, keyword, "test", test_start);
+        }
+        for tag in &test.tags {
+            xml.push_str(&format!("<tag>{}</tag>\n", escape(tag)));
+        }
+        write_status(xml, test.status, test_start, test.elapsed, &test.message);
+        xml.push_str("</test>\n");
+    }
+    for keyword in teardown {
+        write_keyword(xml, keyword, "suite", start);
+    }
+    write_status(
+        xml,
+        suite_status(suite),
+        start,
+        suite.elapsed,
+        &suite.message,
+    );
+    xml.push_str("</suite>\n");
+}
+
+/// Element of a keyword or control structure inside `parent`.
+fn keyword_element(kind: &str, parent: &str) -> &'static str {
+    match (parent, kind) {
+        ("if" | "try", _) => "branch",
+        ("for" | "while", _) => "iter",
+        (_, "FOR") => "for",
+        (_, "WHILE") => "while",
+        (_, "IF" | "IF/ELSE ROOT") => "if",
+        (_, "TRY" | "TRY/EXCEPT ROOT") => "try",
+        (_, "RETURN") => "return",
+        (_, "BREAK") => "break",
+        (_, "CONTINUE") => "continue",
+        // Structures newer than schema 4, such as GROUP, are kept as keywords.
+        _ => "kw",
+    }
+}
+
+fn write_keyword(xml: &mut String, keyword: &Keyword, parent: &str, parent_start: NaiveDateTime) {
+    let start = start_time(keyword.start.as_deref(), parent_start);
+    let element = keyword_element(&keyword.kind, parent);
+    xml.push('<');
+    xml.push_str(element);
+    match element {
+        "kw" => {
+            xml.push_str(&format!(" name=\"{}\"", escape(&keyword.name)));
+            if let Some(library) = &keyword.library {
+                xml.push_str(&format!(" library=\"{}\"", escape(library)));
+            }
+            if matches!(keyword.kind.as_str(), "SETUP" | "TEARDOWN") {
+                xml.push_str(&format!(" type=\"{}\"", keyword.kind));
+            }
+        }
+        "branch" => {
+            xml.push_str(&format!(" type=\"{}\"", escape(&keyword.kind)));
+            if !keyword.name.is_empty() {
+                xml.push_str(&format!(" condition=\"{}\"", escape(&keyword.name)));
+            }
+        }
+        "for" => xml.push_str(" flavor=\"IN\""),
+        "while" if !keyword.name.is_empty() => {
+            xml.push_str(&format!(" condition=\"{}\"", escape(&keyword.name)));
+        }
+        _ => (),
+    }
+    xml.push_str(">\n");
+    for child in &keyword.keywords {
+        write_keyword(xml, child, element, start);
+    }
+    write_status(xml, keyword.status, start, keyword.elapsed, "");
+    xml.push_str(&format!("</{element}>\n"));
+}
+
+/// Failed if any test failed, skipped if all were skipped, passed otherwise.
+fn suite_status(suite: &Suite) -> Status {
+    let statuses = suite
+        .tests
+        .iter()
+        .map(|test| test.status)
+        .chain(suite.suites.iter().map(suite_status))
+        .collect::<Vec<_>>();
+    if statuses.contains(&Status::Fail) {
+        Status::Fail
+    } else if !statuses.is_empty() && statuses.iter().all(|status| *status == Status::Skip) {
+        Status::Skip
+    } else {
+        Status::Pass
+    }
+}
+
+/// The item's own start time, otherwise the start of its parent.
+fn start_time(start: Option<&str>, parent: NaiveDateTime) -> NaiveDateTime {
+    start
+        .and_then(|start| NaiveDateTime::parse_from_str(start, ISO_TIME).ok())
+        .unwrap_or(parent)
+}
+
+fn write_status(
+    xml: &mut String,
+    status: Status,
+    start: NaiveDateTime,
+    elapsed: Option<f64>,
+    message: &str,
+) {
+    let end = elapsed
+        .and_then(|elapsed| TimeDelta::try_milliseconds((elapsed * 1000.0).round() as i64))
+        .and_then(|elapsed| start.checked_add_signed(elapsed))
+        .map_or_else(|| String::from("N/A"), format_time);
+    xml.push_str(&format!(
+        "<status status=\"{}\" starttime=\"{}\" endtime=\"{end}\">{}</status>\n",
+        status.as_str(),
+        format_time(start),
+        escape(message)
+    ));
+}
+
+fn format_time(time: NaiveDateTime) -> String {
+    time.format(OUTPUT_TIME).to_string()
+}
+
+/// Passed, failed and skipped tests
+#[derive(Clone, Copy, Default)]
+struct Counts {
+    pass: usize,
+    fail: usize,
+    skip: usize,
+}
+
+impl Counts {
+    fn add(&mut self, status: Status) {
+        match status {
+            Status::Pass => self.pass += 1,
+            Status::Fail => self.fail += 1,
+            Status::Skip => self.skip += 1,
+            Status::NotRun => (),
+        }
+    }
+
+    fn attributes(&self) -> String {
+        format!(
+            "pass=\"{}\" fail=\"{}\" skip=\"{}\"",
+            self.pass, self.fail, self.skip
+        )
+    }
+}
+
+pub(crate) fn write_statistics(xml: &mut String, result: &RobotResult) {
+    let mut total = Counts::default();
+    let mut tags: BTreeMap<&str, Counts> = BTreeMap::new();
+    for test in result.tests() {
+        total.add(test.test.status);
+        for tag in &test.test.tags {
+            tags.entry(tag).or_default().add(test.test.status);
+        }
+    }
+    xml.push_str(&format!(
+        "<statistics>\n<total>\n<stat {}>All Tests</stat>\n</total>\n<tag>\n",
+        total.attributes()
+    ));
+    for (tag, counts) in tags {
+        xml.push_str(&format!(
+            "<stat {}>{}</stat>\n",
+            counts.attributes(),
+            escape(tag)
+        ));
+    }
+    xml.push_str("</tag>\n<suite>\n");
+    write_suite_statistics(xml, &result.suite, "s1", &result.suite.name);
+    xml.push_str("</suite>\n</statistics>\n");
+}
+
+/// Stats of `suite` and its children, with the ids `write_suite` gives them.
+fn write_suite_statistics(xml: &mut String, suite: &Suite, id: &str, long_name: &str) -> Counts {
+    let mut counts = Counts::default();
+    let mut children = String::new();
+    for test in &suite.tests {
+        counts.add(test.status);
+    }
+    for (index, child) in suite.suites.iter().enumerate() {
+        let child_counts = write_suite_statistics(
+            &mut children,
+            child,
+            &format!("{id}-s{}", index + 1),
+            &format!("{long_name}.{}", child.name),
+        );
+        counts.pass += child_counts.pass;
+        counts.fail += child_counts.fail;
+        counts.skip += child_counts.skip;
+    }
+    xml.push_str(&format!(
+        "<stat {} id=\"{id}\" name=\"{}\">{}</stat>\n",
+        counts.attributes(),
+        escape(&suite.name),
+        escape(long_name)
+    ));
+    xml.push_str(&children);
+    counts
+}
@@ -0,0 +1,141 @@
+//! Robot Framework 7 output.json read into the result model
+use crate::robot::{check_elapsed, Keyword, ParseError, RobotResult, Status, Suite, Test};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct JsonResult {
+    suite: JsonSuite,
+}
+
+#[derive(Deserialize)]
+struct JsonSuite {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    suites: Vec<JsonSuite>,
+    #[serde(default)]
+    tests: Vec<JsonTest>,
+    setup: Option<JsonBody>,
+    teardown: Option<JsonBody>,
+    #[serde(default)]
+    message: String,
+    start: Option<String>,
+    elapsed: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct JsonTest {
+    #[serde(default)]
+    name: String,
+    status: String,
+    #[serde(default)]
+    message: String,
+    start: Option<String>,
+    elapsed: Option<f64>,
+    #[serde(default)]
+    tags: Vec<String>,
+    setup: Option<JsonBody>,
+    teardown: Option<JsonBody>,
+    #[serde(default)]
+    body: Vec<JsonBody>,
+}
+
+/// Keyword, control structure or message in a body
+#[derive(Deserialize)]
+struct JsonBody {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    name: Option<String>,
+    condition: Option<String>,
+    owner: Option<String>,
+    libname: Option<String>,
+    status: Option<String>,
+    start: Option<String>,
+    elapsed: Option<f64>,
+    /// Keyword setup and teardown, since Robot Framework 7.
+    setup: Option<Box<JsonBody>>,
+    teardown: Option<Box<JsonBody>>,
+    #[serde(default)]
+    body: Vec<JsonBody>,
+}
+
+/// Parse a Robot Framework output.json.
+pub fn parse_output_json(json: &str) -> Result<RobotResult, ParseError> {
+    let result: JsonResult =
+        serde_json::from_str(json).map_err(|error| ParseError(error.to_string()))?;
+    Ok(RobotResult {
+        suite: read_suite(result.suite)?,
+    })
+}
+
+fn read_suite(suite: JsonSuite) -> Result<Suite, ParseError> {
+    Ok(Suite {
+        name: suite.name,
+        suites: suite
+            .suites
+            .into_iter()
+            .map(read_suite)
+            .collect::<Result<_, _>>()?,
+        tests: suite
+            .tests
+            .into_iter()
+            .map(read_test)
+            .collect::<Result<_, _>>()?,
+        keywords: fixtures(suite.setup, Vec::new(), suite.teardown)?,
+        message: suite.message,
+        start: suite.start,
+        elapsed: suite.elapsed.map(check_elapsed).transpose()?,
+    })
+}
+
+fn read_test(test: JsonTest) -> Result<Test, ParseError> {
+    Ok(Test {
+        name: test.name,
+        status: test.status.parse()?,
+        message: test.message,
+        start: test.start,
+        elapsed: test.elapsed.map(check_elapsed).transpose()?,
+        tags: test.tags,
+        keywords: fixtures(test.setup, test.body, test.teardown)?,
+    })
+}
+
+/// Setup, body and teardown as one keyword list, the way output.xml has them.
+fn fixtures(
+    setup: Option<JsonBody>,
+    body: Vec<JsonBody>,
+    teardown: Option<JsonBody>,
+) -> Result<Vec<Keyword>, ParseError> {
+    let setup = setup.map(|keyword| read_keyword(keyword, "SETUP"));
+    let teardown = teardown.map(|keyword| read_keyword(keyword, "TEARDOWN"));
+    setup
+        .into_iter()
+        .chain(read_body(body))
+        .chain(teardown)
+        .collect()
+}
+
+fn read_body(body: Vec<JsonBody>) -> impl Iterator<Item = Result<Keyword, ParseError>> {
+    body.into_iter()
+        .filter(|item| item.kind.as_deref() != Some("MESSAGE"))
+        .map(|item| read_keyword(item, "KEYWORD"))
+}
+
+fn read_keyword(item: JsonBody, default_kind: &str) -> Result<Keyword, ParseError> {
+    Ok(Keyword {
+        name: item.name.or(item.condition).unwrap_or_default(),
+        kind: item.kind.unwrap_or_else(|| default_kind.to_string()),
+        library: item.owner.or(item.libname),
+        status: match item.status {
+            Some(status) => status.parse()?,
+            None => Status::NotRun,
+        },
+        start: item.start,
+        elapsed: item.elapsed.map(check_elapsed).transpose()?,
+        keywords: fixtures(
+            item.setup.map(|setup| *setup),
+            item.body,
+            item.teardown.map(|teardown| *teardown),
+        )?,
+    })
+}
@@ -1,184 +1,238 @@
-use std::sync::Arc;
+use std::env;
+use std::sync::{Arc, Mutex};
 
-use crate::TODO_TAG;
+use crate::auth::Auth;
+use crate::todo_store::{self, MemoryTodos, Search, SqliteTodos, TodoRepository};
+use crate::{DatabaseError, RouterState, TODO_TAG};
 use axum::{
-    extract::{Path, Query, State},
-    response::IntoResponse,
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        OriginalUri, Path, Query, State,
+    },
+    response::{IntoResponse, Response},
     Json,
 };
-use hyper::{HeaderMap, StatusCode};
-use serde::{Deserialize, Serialize};
-use tokio::sync::Mutex;
-use utoipa::{IntoParams, ToSchema};
+use axum_test_client::todo::{
+    NewTodo, Todo, TodoError, TodoListQuery, TodoPatch, TodoSearch, TodoUpdate, DEFAULT_TODO_PAGE,
+    MAX_TODO_PAGE,
+};
+use hyper::{
+    header::{self, HeaderValue},
+    HeaderMap, StatusCode,
+};
+use tracing::debug;
 use utoipa_axum::{router::OpenApiRouter, routes};
 
-/// In-memory todo store
-type Store = Mutex<Storage>;
+/// Todo store, used through [`run`]
+type Store = Mutex<Box<dyn TodoRepository>>;
+
+/// Location of the todo database, overridden by `TODO_DB`.
+const DEFAULT_TODO_DB: &str = "todo.sqlite";
 
+/// Number of all todos in a list response
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
+/// Todo items shared by all todo routes
 #[derive(Clone)]
-struct Storage {
-    todo_storage: Vec<Todo>,
-}
+pub struct TodoState(Arc<Store>);
 
-impl Storage {
-    fn new() -> Self {
-        Storage {
-            todo_storage: Vec::default(),
-        }
+impl TodoState {
+    /// Empty in-memory state.
+    pub fn new() -> Self {
+        Self::with_repository(MemoryTodos::default())
     }
-}
 
-/// Item to do.
-#[derive(Serialize, Deserialize, ToSchema, Clone)]
-struct Todo {
-    id: i32,
-    #[schema(example = "Buy groceries")]
-    value: String,
-    done: bool,
-}
+    /// State with the todo database at `path`, `:memory:` keeps it in memory.
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, DatabaseError> {
+        Ok(Self::with_repository(SqliteTodos::open(path)?))
+    }
+
+    /// State with the todo database at `TODO_DB` or `todo.sqlite`.
+    pub fn from_env() -> Result<Self, DatabaseError> {
+        let path = env::var("TODO_DB").unwrap_or_else(|_| String::from(DEFAULT_TODO_DB));
+        Self::open(path)
+    }
 
-#[derive(Serialize, Deserialize, ToSchema, Clone)]
-struct Val {
-    #[schema(example = "Buy groceries")]
-    value: String,
+    pub fn with_repository(repository: impl TodoRepository + 'static) -> Self {
+        TodoState(Arc::new(Mutex::new(Box::new(repository))))
+    }
 }
 
-/// Todo operation errors
-#[derive(Serialize, Deserialize, ToSchema)]
-enum TodoError {
-    /// Todo already exists conflict.
-    #[schema(example = "Todo already exists")]
-    Conflict(String),
-    /// Todo not found by id.
-    #[schema(example = "id = 1")]
-    NotFound(String),
-    /// Todo operation unauthorized
-    #[schema(example = "missing api key")]
-    Unauthorized(String),
+impl Default for TodoState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-pub(super) fn router() -> OpenApiRouter {
-    let store = Arc::new(Mutex::new(Storage::new()));
+/// Routes of the todo api on `state`, the api key is checked by `auth`.
+pub fn router(state: TodoState, auth: Auth) -> OpenApiRouter {
+    let state = RouterState { store: state, auth };
     OpenApiRouter::new()
         .routes(routes!(list_todos, create_todo))
         .routes(routes!(search_todos))
-        .routes(routes!(mark_done, delete_todo))
-        .with_state(store)
+        .routes(routes!(replace_todo, update_todo, delete_todo))
+        .with_state(state)
 }
 
-/// List all Todo items
+/// List Todo items
 ///
-/// List all Todo items from in-memory storage.
+/// List Todo items from storage ordered by id, one page at a time. The `next` link in `Link`
+/// continues the list, `X-Total-Count` is the number of all Todo items.
 #[utoipa::path(
         get,
         path = "",
         tag = TODO_TAG,
+        params(
+            TodoListQuery
+        ),
         responses(
-            (status = 200, description = "List all todos successfully", body = [Todo])
+            (status = 200, description = "List todos successfully", body = [Todo],
+                headers(
+                    ("Link" = String, description = "Link to the next page, missing on the last one"),
+                    ("X-Total-Count" = usize, description = "Number of all todos"),
+                )),
+            (status = 400, description = "Invalid query", body = TodoError),
+            (status = 500, description = "Todo database error", body = TodoError)
         ),
         security(
             ("api_key" = [])
         ),
     )]
-async fn list_todos(State(store): State<Arc<Store>>) -> Json<Vec<Todo>> {
-    let todos = store.lock().await.clone();
-
-    Json(todos.todo_storage)
-}
-
-/// Todo search query
-#[derive(Deserialize, IntoParams)]
-struct TodoSearchQuery {
-    /// Search by value. Search is incase sensitive.
-    value: String,
-    /// Search by `done` status.
-    done: bool,
+async fn list_todos(
+    State(TodoState(store)): State<TodoState>,
+    OriginalUri(uri): OriginalUri,
+    query: Result<Query<TodoListQuery>, QueryRejection>,
+) -> impl IntoResponse {
+    let query = match query {
+        Ok(Query(query)) => query,
+        Err(rejection) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(TodoError::InvalidInput(rejection.body_text())),
+            )
+                .into_response()
+        }
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_TODO_PAGE)
+        .clamp(1, MAX_TODO_PAGE);
+    let page = match run(&store, move |todos| todos.list(query.cursor, limit)).await {
+        Ok(page) => page,
+        Err(error) => return database_error(error).into_response(),
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(TOTAL_COUNT_HEADER, HeaderValue::from(page.total));
+    if let Some(next) = page.next {
+        let link = format!("<{}?cursor={next}&limit={limit}>; rel=\"next\"", uri.path());
+        let link = HeaderValue::try_from(link).expect("uri path is a valid header value");
+        headers.insert(header::LINK, link);
+    }
+    (headers, Json(page.todos)).into_response()
 }
 
 /// Search Todos by query params.
 ///
-/// Search `Todo`s by query params and return matching `Todo`s.
+/// Search `Todo`s by query params and return matching `Todo`s. Without params all are returned.
 #[utoipa::path(
         get,
         path = "/search",
         tag = TODO_TAG,
         params(
-            TodoSearchQuery
+            TodoSearch
         ),
         responses(
-            (status = 200, description = "List matching todos by query", body = [Todo])
+            (status = 200, description = "List matching todos by query", body = [Todo]),
+            (status = 400, description = "Invalid query", body = TodoError, example = json!(TodoError::InvalidInput(String::from("regex parse error")))),
+            (status = 500, description = "Todo database error", body = TodoError)
         ),
         security(
             ("api_key" = [])
         ),
     )]
 async fn search_todos(
-    State(store): State<Arc<Store>>,
-    query: Query<TodoSearchQuery>,
-) -> Json<Vec<Todo>> {
-    Json(
-        store
-            .lock()
-            .await
-            .todo_storage
-            .iter()
-            .filter(|todo| {
-                todo.value.to_lowercase() == query.value.to_lowercase() && todo.done == query.done
-            })
-            .cloned()
-            .collect(),
-    )
+    State(TodoState(store)): State<TodoState>,
+    query: Result<Query<TodoSearch>, QueryRejection>,
+) -> impl IntoResponse {
+    let search = match query {
+        Ok(Query(query)) => Search::new(&query).map_err(|error| error.to_string()),
+        Err(rejection) => Err(rejection.body_text()),
+    };
+    let search = match search {
+        Ok(search) => search,
+        Err(error) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(TodoError::InvalidInput(error)),
+            )
+                .into_response()
+        }
+    };
+    match run(&store, move |todos| todos.search(&search)).await {
+        Ok(todos) => Json(todos).into_response(),
+        Err(error) => database_error(error).into_response(),
+    }
 }
 
 /// Create new Todo
 ///
-/// Tries to create a new Todo item to in-memory storage or fails with 409 conflict if already exists.
+/// Create a new Todo item in storage with the next free id. The path of the new item is in `Location`.
 #[utoipa::path(
         post,
         path = "",
         tag = TODO_TAG,
+        request_body = NewTodo,
         responses(
-            (status = 201, description = "Todo item created successfully", body = Todo),
-            (status = 409, description = "Todo already exists", body = TodoError)
+            (status = 201, description = "Todo item created successfully", body = Todo,
+                headers(("Location" = String, description = "Path of the new todo"))),
+            (status = 422, description = "Invalid todo", body = TodoError, example = json!(TodoError::InvalidInput(String::from("value must not be empty")))),
+            (status = 500, description = "Todo database error", body = TodoError)
         ),
         security(
             ("api_key" = [])
         ),
     )]
-async fn create_todo(State(store): State<Arc<Store>>, Json(todo): Json<Todo>) -> impl IntoResponse {
-    let mut todos = store.lock().await;
-
-    todos
-        .todo_storage
-        .iter_mut()
-        .find(|existing_todo| existing_todo.id == todo.id)
-        .map(|found| {
+async fn create_todo(
+    State(TodoState(store)): State<TodoState>,
+    OriginalUri(uri): OriginalUri,
+    todo: Result<Json<NewTodo>, JsonRejection>,
+) -> impl IntoResponse {
+    let todo = match todo {
+        Ok(Json(todo)) => todo,
+        Err(rejection) => return invalid_input(rejection.body_text()).into_response(),
+    };
+    if let Err(error) = check_value(&todo.value) {
+        return error.into_response();
+    }
+    match run(&store, move |todos| todos.create(&todo.value)).await {
+        Ok(todo) => {
+            let location = format!("{}/{}", uri.path().trim_end_matches('/'), todo.id);
             (
-                StatusCode::CONFLICT,
-                Json(TodoError::Conflict(format!(
-                    "todo already exists: {}",
-                    found.id
-                ))),
+                StatusCode::CREATED,
+                [(header::LOCATION, location)],
+                Json(todo),
             )
                 .into_response()
-        })
-        .unwrap_or_else(|| {
-            todos.todo_storage.push(todo.clone());
-
-            (StatusCode::CREATED, Json(todo)).into_response()
-        })
+        }
+        Err(error) => database_error(error).into_response(),
+    }
 }
 
-/// Mark Todo item done by id
+/// Replace Todo item by id
 ///
-/// Mark Todo item done by given id. Return only status 200 on success or 404 if Todo is not found.
+/// Replace value and done status of the Todo item with given id and return the updated item.
+/// The api key is optional, an incorrect one is rejected with 401.
 #[utoipa::path(
         put,
         path = "/{id}",
         tag = TODO_TAG,
+        request_body = TodoUpdate,
         responses(
-            (status = 200, description = "Todo marked done successfully"),
-            (status = 404, description = "Todo not found")
+            (status = 200, description = "Todo replaced successfully", body = Todo),
+            (status = 401, description = "Incorrect api key", body = TodoError, example = json!(TodoError::Unauthorized(String::from("incorrect api key")))),
+            (status = 404, description = "Todo not found", body = TodoError, example = json!(TodoError::NotFound(String::from("id = 1")))),
+            (status = 422, description = "Invalid todo", body = TodoError, example = json!(TodoError::InvalidInput(String::from("value must not be empty")))),
+            (status = 500, description = "Todo database error", body = TodoError)
         ),
         params(
             ("id" = i32, Path, description = "Todo database id")
@@ -187,40 +241,106 @@ async fn create_todo(State(store): State<Arc<Store>>, Json(todo): Json<Todo>) ->
             ("api_key" = [])
         ),
     )]
-async fn mark_done(
+async fn replace_todo(
     Path(id): Path<i32>,
-    State(store): State<Arc<Store>>,
+    State(TodoState(store)): State<TodoState>,
+    State(auth): State<Auth>,
     headers: HeaderMap,
-) -> StatusCode {
-    match check_api_key(false, headers) {
+    update: Result<Json<TodoUpdate>, JsonRejection>,
+) -> impl IntoResponse {
+    match check_api_key(&auth, false, headers) {
         Ok(_) => (),
-        Err(_) => return StatusCode::UNAUTHORIZED,
+        Err(error) => return error.into_response(),
+    }
+    match update {
+        Ok(Json(update)) => patch_todo(&store, id, update.into()).await,
+        Err(rejection) => invalid_input(rejection.body_text()).into_response(),
     }
+}
 
-    let mut todos = store.lock().await;
+/// Update Todo item by id
+///
+/// Change the fields given in the body of the Todo item with given id and return the updated item.
+/// The api key is optional, an incorrect one is rejected with 401.
+#[utoipa::path(
+        patch,
+        path = "/{id}",
+        tag = TODO_TAG,
+        request_body = TodoPatch,
+        responses(
+            (status = 200, description = "Todo updated successfully", body = Todo),
+            (status = 401, description = "Incorrect api key", body = TodoError, example = json!(TodoError::Unauthorized(String::from("incorrect api key")))),
+            (status = 404, description = "Todo not found", body = TodoError, example = json!(TodoError::NotFound(String::from("id = 1")))),
+            (status = 422, description = "Invalid todo", body = TodoError, example = json!(TodoError::InvalidInput(String::from("value must not be empty")))),
+            (status = 500, description = "Todo database error", body = TodoError)
+        ),
+        params(
+            ("id" = i32, Path, description = "Todo database id")
+        ),
+        security(
+            ("api_key" = [])
+        ),
+    )]
+async fn update_todo(
+    Path(id): Path<i32>,
+    State(TodoState(store)): State<TodoState>,
+    State(auth): State<Auth>,
+    headers: HeaderMap,
+    patch: Result<Json<TodoPatch>, JsonRejection>,
+) -> impl IntoResponse {
+    match check_api_key(&auth, false, headers) {
+        Ok(_) => (),
+        Err(error) => return error.into_response(),
+    }
+    match patch {
+        Ok(Json(patch)) => patch_todo(&store, id, patch).await,
+        Err(rejection) => invalid_input(rejection.body_text()).into_response(),
+    }
+}
+
+async fn patch_todo(store: &Arc<Store>, id: i32, patch: TodoPatch) -> Response {
+    if let Some(value) = &patch.value {
+        if let Err(error) = check_value(value) {
+            return error.into_response();
+        }
+    }
+    match run(store, move |todos| todos.update(id, &patch)).await {
+        Ok(Some(todo)) => Json(todo).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(TodoError::NotFound(format!("id = {id}"))),
+        )
+            .into_response(),
+        Err(error) => database_error(error).into_response(),
+    }
+}
 
-    todos
-        .todo_storage
-        .iter_mut()
-        .find(|todo| todo.id == id)
-        .map(|todo| {
-            todo.done = true;
-            StatusCode::OK
-        })
-        .unwrap_or(StatusCode::NOT_FOUND)
+fn check_value(value: &str) -> Result<(), (StatusCode, Json<TodoError>)> {
+    if value.trim().is_empty() {
+        return Err(invalid_input(String::from("value must not be empty")));
+    }
+    Ok(())
+}
+
+fn invalid_input(message: String) -> (StatusCode, Json<TodoError>) {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(TodoError::InvalidInput(message)),
+    )
 }
 
 /// Delete Todo item by id
 ///
-/// Delete Todo item from in-memory storage by id. Returns either 200 success of 404 with TodoError if Todo is not found.
+/// Delete Todo item from storage by id. Returns either 200 success of 404 with TodoError if Todo is not found.
 #[utoipa::path(
         delete,
         path = "/{id}",
         tag = TODO_TAG,
         responses(
-            (status = 200, description = "Todo marked done successfully"),
+            (status = 200, description = "Todo deleted successfully"),
             (status = 401, description = "Unauthorized to delete Todo", body = TodoError, example = json!(TodoError::Unauthorized(String::from("missing api key")))),
-            (status = 404, description = "Todo not found", body = TodoError, example = json!(TodoError::NotFound(String::from("id = 1"))))
+            (status = 404, description = "Todo not found", body = TodoError, example = json!(TodoError::NotFound(String::from("id = 1")))),
+            (status = 500, description = "Todo database error", body = TodoError)
         ),
         params(
             ("id" = i32, Path, description = "Todo database id")
@@ -231,46 +351,56 @@ async fn mark_done(
     )]
 async fn delete_todo(
     Path(id): Path<i32>,
-    State(store): State<Arc<Store>>,
+    State(TodoState(store)): State<TodoState>,
+    State(auth): State<Auth>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
-    match check_api_key(true, headers) {
+    match check_api_key(&auth, true, headers) {
         Ok(_) => (),
         Err(error) => return error.into_response(),
     }
 
-    let mut state = store.lock().await;
-    let todos = &mut state.todo_storage;
-
-    let len = todos.len();
-
-    todos.retain(|todo| todo.id != id);
-
-    if todos.len() != len {
-        StatusCode::OK.into_response()
-    } else {
-        (
+    match run(&store, move |todos| todos.delete(id)).await {
+        Ok(true) => StatusCode::OK.into_response(),
+        Ok(false) => (
             StatusCode::NOT_FOUND,
             Json(TodoError::NotFound(format!("id = {id}"))),
         )
-            .into_response()
+            .into_response(),
+        Err(error) => database_error(error).into_response(),
     }
 }
 
-// normally you should create a middleware for this but this is sufficient for sake of example.
+/// Run `operation` on the store in a blocking thread, the database calls block.
+async fn run<T: Send + 'static>(
+    store: &Arc<Store>,
+    operation: impl FnOnce(&mut dyn TodoRepository) -> todo_store::Result<T> + Send + 'static,
+) -> todo_store::Result<T> {
+    let store = store.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut todos = store.lock().map_err(|_| "todo store poisoned")?;
+        operation(todos.as_mut())
+    })
+    .await?
+}
+
+fn database_error(error: todo_store::Error) -> (StatusCode, Json<TodoError>) {
+    debug!("Todo database error: {error}");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(TodoError::Database(error.to_string())),
+    )
+}
+
 fn check_api_key(
+    auth: &Auth,
     require_api_key: bool,
     headers: HeaderMap,
 ) -> Result<(), (StatusCode, Json<TodoError>)> {
-    match headers.get("theapikey") {
-        Some(header) if header != "rocks" => Err((
-            StatusCode::UNAUTHORIZED,
-            Json(TodoError::Unauthorized(String::from("incorrect api key"))),
-        )),
-        None if require_api_key => Err((
+    auth.check(&headers, require_api_key).map_err(|error| {
+        (
             StatusCode::UNAUTHORIZED,
-            Json(TodoError::Unauthorized(String::from("missing api key"))),
-        )),
-        _ => Ok(()),
-    }
+            Json(TodoError::Unauthorized(error)),
+        )
+    })
 }
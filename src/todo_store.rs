@@ -0,0 +1,357 @@
+//! Storage of the todo items
+//!
+//! The todo api works on a [`TodoRepository`]. [`SqliteTodos`] keeps the items
+//! across restarts, [`MemoryTodos`] is for tests and throwaway servers.
+use crate::{migrations, DatabaseError};
+use axum_test_client::todo::{MatchMode, SortOrder, Todo, TodoPatch, TodoSearch, TodoSort};
+use regex::{Regex, RegexBuilder};
+use rusqlite::functions::{Context, FunctionFlags};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+
+/// Failure of the underlying storage
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Todos following a cursor
+pub struct Page {
+    pub todos: Vec<Todo>,
+    /// Number of all stored todos.
+    pub total: usize,
+    /// Cursor of the next page, None on the last one.
+    pub next: Option<i32>,
+}
+
+/// Storage of the todo items, ordered by id
+pub trait TodoRepository: Send {
+    /// Up to `limit` todos with an id greater than `after`.
+    fn list(&self, after: Option<i32>, limit: usize) -> Result<Page>;
+
+    /// Todos matching `search`, in its order and page.
+    fn search(&self, search: &Search) -> Result<Vec<Todo>>;
+
+    /// Store a new todo, ids increase and are not reused after deletion.
+    fn create(&mut self, value: &str) -> Result<Todo>;
+
+    /// Change the fields set in `patch`, None if there is no todo with `id`.
+    fn update(&mut self, id: i32, patch: &TodoPatch) -> Result<Option<Todo>>;
+
+    /// Remove the todo, false if there is none with `id`.
+    fn delete(&mut self, id: i32) -> Result<bool>;
+}
+
+/// A [`TodoSearch`] ready to be applied
+pub struct Search {
+    value: Option<ValueMatch>,
+    done: Option<bool>,
+    sort: TodoSort,
+    order: SortOrder,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+/// Value to find, lowercase for the plain text modes
+enum ValueMatch {
+    Exact(String),
+    Substring(String),
+    Prefix(String),
+    Regex(Regex),
+}
+
+impl Search {
+    /// Fails on an invalid regular expression.
+    pub fn new(search: &TodoSearch) -> std::result::Result<Self, regex::Error> {
+        let value = match &search.value {
+            None => None,
+            Some(value) => Some(match search.mode.unwrap_or_default() {
+                MatchMode::Exact => ValueMatch::Exact(value.to_lowercase()),
+                MatchMode::Substring => ValueMatch::Substring(value.to_lowercase()),
+                MatchMode::Prefix => ValueMatch::Prefix(value.to_lowercase()),
+                MatchMode::Regex => {
+                    ValueMatch::Regex(RegexBuilder::new(value).case_insensitive(true).build()?)
+                }
+            }),
+        };
+        Ok(Search {
+            value,
+            done: search.done,
+            sort: search.sort.unwrap_or_default(),
+            order: search.order.unwrap_or_default(),
+            limit: search.limit,
+            offset: search.offset.unwrap_or_default(),
+        })
+    }
+
+    fn matches(&self, todo: &Todo) -> bool {
+        if self.done.is_some_and(|done| todo.done != done) {
+            return false;
+        }
+        match &self.value {
+            None => true,
+            Some(ValueMatch::Regex(regex)) => regex.is_match(&todo.value),
+            Some(ValueMatch::Exact(value)) => todo.value.to_lowercase() == *value,
+            Some(ValueMatch::Substring(value)) => todo.value.to_lowercase().contains(value),
+            Some(ValueMatch::Prefix(value)) => todo.value.to_lowercase().starts_with(value),
+        }
+    }
+
+    /// Matching todos of `todos`, sorted and paged.
+    fn apply<'a>(&self, todos: impl IntoIterator<Item = &'a Todo>) -> Vec<Todo> {
+        let mut found: Vec<&Todo> = todos
+            .into_iter()
+            .filter(|todo| self.matches(todo))
+            .collect();
+        found.sort_by(|a, b| {
+            let ordering = match self.sort {
+                TodoSort::Id => a.id.cmp(&b.id),
+                TodoSort::Value => a.value.to_lowercase().cmp(&b.value.to_lowercase()),
+                TodoSort::Done => a.done.cmp(&b.done),
+            }
+            .then(a.id.cmp(&b.id));
+            match self.order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            }
+        });
+        found
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Todos in memory, lost on restart
+#[derive(Default)]
+pub struct MemoryTodos {
+    todos: Vec<Todo>,
+    last_id: i32,
+}
+
+impl TodoRepository for MemoryTodos {
+    fn list(&self, after: Option<i32>, limit: usize) -> Result<Page> {
+        let start = after.map_or(0, |after| {
+            self.todos.partition_point(|todo| todo.id <= after)
+        });
+        let rest = &self.todos[start..];
+        let todos = rest[..rest.len().min(limit)].to_vec();
+        let next = match rest.len() > limit {
+            true => todos.last().map(|todo| todo.id),
+            false => None,
+        };
+        Ok(Page {
+            todos,
+            total: self.todos.len(),
+            next,
+        })
+    }
+
+    fn search(&self, search: &Search) -> Result<Vec<Todo>> {
+        Ok(search.apply(&self.todos))
+    }
+
+    fn create(&mut self, value: &str) -> Result<Todo> {
+        self.last_id += 1;
+        let todo = Todo {
+            id: self.last_id,
+            value: String::from(value),
+            done: false,
+        };
+        self.todos.push(todo.clone());
+        Ok(todo)
+    }
+
+    fn update(&mut self, id: i32, patch: &TodoPatch) -> Result<Option<Todo>> {
+        let Some(todo) = self.todos.iter_mut().find(|todo| todo.id == id) else {
+            return Ok(None);
+        };
+        if let Some(value) = &patch.value {
+            todo.value = value.clone();
+        }
+        if let Some(done) = patch.done {
+            todo.done = done;
+        }
+        Ok(Some(todo.clone()))
+    }
+
+    fn delete(&mut self, id: i32) -> Result<bool> {
+        let len = self.todos.len();
+        self.todos.retain(|todo| todo.id != id);
+        Ok(self.todos.len() != len)
+    }
+}
+
+/// Schema changes in order, see [`migrations::apply`].
+const MIGRATIONS: &[&str] = &[
+    // Ids of deleted todos are not handed out again.
+    "
+CREATE TABLE todos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    value TEXT NOT NULL,
+    done INTEGER NOT NULL DEFAULT 0
+);
+",
+];
+
+/// Todos in a SQLite database
+pub struct SqliteTodos {
+    connection: Connection,
+}
+
+impl SqliteTodos {
+    /// Open the database at `path`, `:memory:` keeps it in memory. Pending migrations are applied.
+    pub fn open<P: AsRef<Path>>(path: P) -> std::result::Result<Self, DatabaseError> {
+        let mut connection = Connection::open(path)?;
+        migrations::apply(&mut connection, MIGRATIONS)?;
+        register_functions(&connection)?;
+        Ok(SqliteTodos { connection })
+    }
+
+    fn select(
+        &self,
+        filter: &str,
+        order: &str,
+        limit: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<Todo>> {
+        let mut select = self.connection.prepare(&format!(
+            "SELECT id, value, done FROM todos {filter} ORDER BY {order} {limit}"
+        ))?;
+        let rows = select.query_map(params, todo_from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+/// `casefold(value)` lowercases like [`str::to_lowercase`], SQLite's `lower` only knows ASCII.
+/// `value REGEXP pattern` matches a [`Regex`], compiled once per statement.
+fn register_functions(connection: &Connection) -> rusqlite::Result<()> {
+    let flags = FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC;
+    connection.create_scalar_function("casefold", 1, flags, |context| {
+        Ok(context.get::<String>(0)?.to_lowercase())
+    })?;
+    connection.create_scalar_function("regexp", 2, flags, |context: &Context| {
+        let regex = context.get_or_create_aux(0, |pattern| -> Result<Regex> {
+            Ok(Regex::new(pattern.as_str()?)?)
+        })?;
+        Ok(regex.is_match(&context.get::<String>(1)?))
+    })
+}
+
+/// `value` with the LIKE wildcards escaped by a backslash.
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+fn todo_from_row(row: &rusqlite::Row) -> rusqlite::Result<Todo> {
+    Ok(Todo {
+        id: row.get(0)?,
+        value: row.get(1)?,
+        done: row.get(2)?,
+    })
+}
+
+impl TodoRepository for SqliteTodos {
+    fn list(&self, after: Option<i32>, limit: usize) -> Result<Page> {
+        // One more than asked for tells whether there is a next page.
+        let mut todos = self.select(
+            "WHERE ?1 IS NULL OR id > ?1",
+            "id",
+            "LIMIT ?2",
+            params![
+                after,
+                i64::try_from(limit.saturating_add(1)).unwrap_or(i64::MAX)
+            ],
+        )?;
+        let next = match todos.len() > limit {
+            true => {
+                todos.truncate(limit);
+                todos.last().map(|todo| todo.id)
+            }
+            false => None,
+        };
+        let total: i64 = self
+            .connection
+            .query_row("SELECT COUNT(*) FROM todos", [], |row| row.get(0))?;
+        Ok(Page {
+            todos,
+            total: total.try_into()?,
+            next,
+        })
+    }
+
+    /// Matches like [`MemoryTodos`], plain text modes on `casefold(value)`.
+    fn search(&self, search: &Search) -> Result<Vec<Todo>> {
+        let (value_filter, value) = match &search.value {
+            None => ("?2 IS NULL", None),
+            Some(ValueMatch::Exact(value)) => ("casefold(value) = ?2", Some(value.clone())),
+            Some(ValueMatch::Substring(value)) => (
+                "casefold(value) LIKE ?2 ESCAPE '\\'",
+                Some(format!("%{}%", escape_like(value))),
+            ),
+            Some(ValueMatch::Prefix(value)) => (
+                "casefold(value) LIKE ?2 ESCAPE '\\'",
+                Some(format!("{}%", escape_like(value))),
+            ),
+            Some(ValueMatch::Regex(regex)) => {
+                ("value REGEXP ?2", Some(format!("(?i){}", regex.as_str())))
+            }
+        };
+        let direction = match search.order {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        };
+        let order = match search.sort {
+            TodoSort::Id => format!("id {direction}"),
+            TodoSort::Value => format!("casefold(value) {direction}, id {direction}"),
+            TodoSort::Done => format!("done {direction}, id {direction}"),
+        };
+        let limit = search
+            .limit
+            .map_or(-1, |limit| i64::try_from(limit).unwrap_or(i64::MAX));
+        self.select(
+            &format!("WHERE (?1 IS NULL OR done = ?1) AND {value_filter}"),
+            &order,
+            "LIMIT ?3 OFFSET ?4",
+            params![
+                search.done,
+                value,
+                limit,
+                i64::try_from(search.offset).unwrap_or(i64::MAX)
+            ],
+        )
+    }
+
+    fn create(&mut self, value: &str) -> Result<Todo> {
+        self.connection
+            .execute("INSERT INTO todos (value) VALUES (?1)", [value])?;
+        Ok(Todo {
+            id: self.connection.last_insert_rowid().try_into()?,
+            value: String::from(value),
+            done: false,
+        })
+    }
+
+    fn update(&mut self, id: i32, patch: &TodoPatch) -> Result<Option<Todo>> {
+        Ok(self
+            .connection
+            .query_row(
+                "UPDATE todos SET value = COALESCE(?2, value), done = COALESCE(?3, done)
+                 WHERE id = ?1 RETURNING id, value, done",
+                params![id, patch.value, patch.done],
+                todo_from_row,
+            )
+            .optional()?)
+    }
+
+    fn delete(&mut self, id: i32) -> Result<bool> {
+        let deleted = self
+            .connection
+            .execute("DELETE FROM todos WHERE id = ?1", [id])?;
+        Ok(deleted == 1)
+    }
+}
@@ -0,0 +1,869 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use axum_test_client::analysis::{BlendSummary, Comparison, FailureCluster, FlakyTest, Timing};
+use axum_test_client::blend::{
+    BlendError, BlendFile, BlendRequest, CompareInput, CompareRequest, DEFAULT_DURATION_THRESHOLD,
+};
+use axum_test_client::history::{TestOutcome, TrendPoint};
+use axum_test_client::robot::{Keyword, RobotResult, Status};
+use common::{fixture, request, TestApp, TestResponse, API_KEY};
+use serde_json::json;
+
+async fn list(app: &TestApp) -> Vec<String> {
+    let response = app.get("/api/v1/blend/list").await;
+    assert_eq!(response.status, StatusCode::OK);
+    response.json()
+}
+
+#[tokio::test]
+async fn upload_list_and_clear() {
+    let app = TestApp::new();
+    assert!(list(&app).await.is_empty());
+
+    assert_eq!(app.upload("output_a.xml").await.status, StatusCode::OK);
+    assert_eq!(app.upload("output_b.xml").await.status, StatusCode::OK);
+    assert_eq!(list(&app).await, ["output_a.xml", "output_b.xml"]);
+
+    assert_eq!(
+        app.delete("/api/v1/blend/list").await.status,
+        StatusCode::OK
+    );
+    assert!(list(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn upload_rejects_duplicate_name() {
+    let app = TestApp::new();
+    app.upload("output_a.xml").await;
+    let response = app
+        .post("/api/v1/blend/upload/output_a.xml", fixture("output_b.xml"))
+        .await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+    assert!(matches!(response.json(), BlendError::Conflict(_)));
+    assert_eq!(list(&app).await, ["output_a.xml"]);
+}
+
+#[tokio::test]
+async fn upload_names_with_directories() {
+    let app = TestApp::new();
+    for run in ["a", "b"] {
+        let response = app
+            .post(
+                &format!("/api/v1/blend/upload/{run}%2Foutput.xml"),
+                fixture("output_a.xml"),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+    }
+    assert_eq!(list(&app).await, ["a/output.xml", "b/output.xml"]);
+}
+
+#[tokio::test]
+async fn upload_converts_junit() {
+    let app = TestApp::new();
+    assert_eq!(app.upload("junit.xml").await.status, StatusCode::OK);
+
+    let summary: BlendSummary = app.get("/api/v1/blend/summary").await.json();
+    assert_eq!(summary.files.len(), 1);
+    assert_eq!(summary.files[0].total, 2);
+    assert_eq!(summary.files[0].failed, 1);
+}
+
+fn kinds(keywords: &[Keyword]) -> Vec<&str> {
+    keywords
+        .iter()
+        .map(|keyword| keyword.kind.as_str())
+        .collect()
+}
+
+#[tokio::test]
+async fn convert_output_json() {
+    let app = TestApp::new();
+    let response = app
+        .post("/api/v1/blend/xml?format=json", fixture("output_c.json"))
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let result: RobotResult = response.json();
+
+    let login = &result.suite.suites[0];
+    assert_eq!(kinds(&login.keywords), ["SETUP", "TEARDOWN"]);
+    let valid_login = &login.tests[0];
+    assert_eq!(valid_login.tags, ["smoke"]);
+    let log_in = &valid_login.keywords[0];
+    assert_eq!(log_in.name, "Log In As");
+    assert_eq!(
+        kinds(&log_in.keywords),
+        ["SETUP", "KEYWORD", "KEYWORD", "TEARDOWN"]
+    );
+    assert_eq!(log_in.keywords[0].name, "Go To Login Page");
+    assert_eq!(log_in.keywords[3].name, "Capture Page Screenshot");
+    // messages are not keywords
+    assert!(log_in.keywords[1].keywords.is_empty());
+
+    let add_item = &result.suite.suites[1].tests[0];
+    assert_eq!(add_item.status, Status::Fail);
+    assert_eq!(
+        add_item.message,
+        "Element 'id=add-apple' not visible after 5 seconds."
+    );
+    assert_eq!(kinds(&add_item.keywords), ["FOR"]);
+}
+
+#[tokio::test]
+async fn mixed_xml_and_json_session() {
+    let app = TestApp::new();
+    for name in ["output_a.xml", "output_b.xml", "output_c.json"] {
+        assert_eq!(app.upload(name).await.status, StatusCode::OK, "{name}");
+    }
+    assert_eq!(app.get("/api/v1/blend/blend").await.status, StatusCode::OK);
+
+    let summary: BlendSummary = app.get("/api/v1/blend/summary").await.json();
+    assert_eq!(summary.total_tests, 3);
+    let failed: Vec<usize> = summary.files.iter().map(|file| file.failed).collect();
+    assert_eq!(failed, [0, 1, 1]);
+    assert_eq!(summary.newly_failing, ["Tests.Cart.Add Item"]);
+    let statuses = |test: &str| {
+        summary
+            .differing
+            .iter()
+            .find(|differing| differing.test == test)
+            .map(|differing| differing.statuses.clone())
+    };
+    assert_eq!(
+        statuses("Tests.Login.Invalid Login"),
+        Some(vec![
+            Some(Status::Pass),
+            Some(Status::Fail),
+            Some(Status::Pass)
+        ])
+    );
+    assert_eq!(
+        statuses("Tests.Cart.Add Item"),
+        Some(vec![
+            Some(Status::Pass),
+            Some(Status::Pass),
+            Some(Status::Fail)
+        ])
+    );
+
+    let timing: Timing = app.get("/api/v1/blend/timing").await.json();
+    let calls = |keyword: &str| {
+        timing
+            .keywords
+            .iter()
+            .find(|timing| timing.keyword == keyword)
+            .map(|timing| timing.calls)
+    };
+    assert_eq!(calls("SeleniumLibrary.Open Browser"), Some(3));
+    assert_eq!(calls("SeleniumLibrary.Input Text"), Some(6));
+    assert_eq!(calls("SeleniumLibrary.Click Element"), Some(3));
+    assert_eq!(calls("common.Go To Login Page"), Some(1));
+    assert_eq!(calls("SeleniumLibrary.Capture Page Screenshot"), Some(1));
+    assert_eq!(calls("SeleniumLibrary.Close Browser"), Some(1));
+}
+
+#[tokio::test]
+async fn upload_rejects_broken_json() {
+    let app = TestApp::new();
+    let response = app
+        .post("/api/v1/blend/upload/output.json", "{ \"suite\": ")
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(matches!(response.json(), BlendError::InvalidInput(_)));
+    assert!(list(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn upload_rejects_unknown_format() {
+    let app = TestApp::new();
+    for data in ["<html><body/></html>", "plain text", ""] {
+        let response = app.post("/api/v1/blend/upload/page.html", data).await;
+        assert_eq!(response.status, StatusCode::BAD_REQUEST, "{data}");
+        assert!(matches!(response.json(), BlendError::InvalidInput(_)));
+    }
+    assert!(list(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn upload_rejects_invalid_elapsed_times() {
+    let app = TestApp::new();
+    let uploads = [
+        (
+            "huge.xml",
+            r#"<testsuite name="cart"><testcase name="add" time="1e300"/></testsuite>"#,
+        ),
+        (
+            "nan.xml",
+            r#"<testsuite name="cart"><testcase name="add" time="NaN"/></testsuite>"#,
+        ),
+        (
+            "negative.json",
+            r#"{"suite": {"name": "Cart", "tests": [{"name": "Add", "status": "PASS", "elapsed": -1.5}]}}"#,
+        ),
+    ];
+    for (name, data) in uploads {
+        let response = app
+            .post(&format!("/api/v1/blend/upload/{name}"), data)
+            .await;
+        assert_eq!(response.status, StatusCode::BAD_REQUEST, "{name}");
+        assert!(matches!(response.json(), BlendError::InvalidInput(_)));
+    }
+    assert!(list(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn blend_all_files() {
+    let app = TestApp::new();
+    app.upload("output_a.xml").await;
+    app.upload("output_b.xml").await;
+
+    let response = app.get("/api/v1/blend/blend").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.body.is_empty());
+    assert_eq!(list(&app).await.len(), 2);
+
+    let response = app.get("/api/v1/blend/blend?consume=true").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(list(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn blend_selected_files() {
+    let app = TestApp::new();
+    app.upload("output_a.xml").await;
+    app.upload("output_b.xml").await;
+
+    let request = BlendRequest {
+        files: vec![
+            BlendFile {
+                name: String::from("output_b.xml"),
+                display_name: Some(String::from("Run B")),
+            },
+            BlendFile {
+                name: String::from("output_a.xml"),
+                display_name: None,
+            },
+        ],
+        ..BlendRequest::default()
+    };
+    let response = app.post_json("/api/v1/blend/blend", &request).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.body.is_empty());
+
+    let response = app
+        .post_json(
+            "/api/v1/blend/blend",
+            &json!({"files": [{"name": "missing.xml"}]}),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert!(matches!(response.json(), BlendError::NotFound(_)));
+
+    let response = app
+        .post_json(
+            "/api/v1/blend/blend",
+            &json!({"files": [
+                {"name": "output_a.xml", "display_name": "Run"},
+                {"name": "output_b.xml", "display_name": "Run"}
+            ]}),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+    assert!(matches!(response.json(), BlendError::Conflict(_)));
+}
+
+#[tokio::test]
+async fn summary_reports_newly_failing() {
+    let app = TestApp::new();
+    app.upload("output_a.xml").await;
+    app.upload("output_b.xml").await;
+
+    let summary: BlendSummary = app.get("/api/v1/blend/summary").await.json();
+    assert_eq!(summary.total_tests, 3);
+    assert_eq!(summary.files[0].failed, 0);
+    assert_eq!(summary.files[1].failed, 1);
+    assert_eq!(summary.newly_failing, ["Tests.Login.Invalid Login"]);
+}
+
+#[tokio::test]
+async fn flaky_test_failing_in_one_run() {
+    let app = TestApp::new();
+    app.upload("output_a.xml").await;
+    app.upload("output_b.xml").await;
+
+    let response = app.get("/api/v1/blend/flaky").await;
+    assert_eq!(response.status, StatusCode::OK);
+    let flaky: Vec<FlakyTest> = response.json();
+    assert_eq!(flaky.len(), 1);
+    let test = &flaky[0];
+    assert_eq!(test.test, "Tests.Login.Invalid Login");
+    assert_eq!((test.runs, test.passed, test.failed), (2, 1, 1));
+    assert_eq!(test.flakiness, 0.5);
+    assert_eq!(test.failures.len(), 1);
+    assert_eq!(test.failures[0].run, "output_b.xml");
+    assert_eq!(
+        test.failures[0].message,
+        "Page should have contained text 'Login failed' but did not."
+    );
+}
+
+/// Blend `file` and record it for the shop project at `timestamp`.
+async fn record(app: &TestApp, file: &str, timestamp: &str) -> TestResponse {
+    let request = json!({
+        "files": [{"name": file}],
+        "record": {"project": "shop", "branch": "main", "timestamp": timestamp}
+    });
+    app.post_json("/api/v1/blend/blend", &request).await
+}
+
+#[tokio::test]
+async fn history_and_trends() {
+    let app = TestApp::new();
+    app.upload("output_a.xml").await;
+    app.upload("output_b.xml").await;
+    // Recorded first but ran later, the offset must not decide the order.
+    let response = record(&app, "output_a.xml", "2024-05-02T08:00:00-03:00").await;
+    assert_eq!(response.status, StatusCode::OK);
+    let response = record(&app, "output_b.xml", "2024-05-02T09:30:00Z").await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = record(&app, "output_b.xml", "yesterday").await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(matches!(response.json(), BlendError::InvalidInput(_)));
+
+    let response = app
+        .get("/api/v1/blend/history/shop/tests/Tests.Login.Invalid%20Login")
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let outcomes: Vec<TestOutcome> = response.json();
+    let outcomes: Vec<(&str, &str, Status)> = outcomes
+        .iter()
+        .map(|x| (x.timestamp.as_str(), x.run.as_str(), x.status))
+        .collect();
+    assert_eq!(
+        outcomes,
+        [
+            ("2024-05-02T09:30:00Z", "output_b.xml", Status::Fail),
+            ("2024-05-02T11:00:00Z", "output_a.xml", Status::Pass),
+        ]
+    );
+
+    let trends: Vec<TrendPoint> = app.get("/api/v1/blend/history/shop/trends").await.json();
+    let counts: Vec<(usize, usize, usize)> = trends
+        .iter()
+        .map(|x| (x.total, x.passed, x.failed))
+        .collect();
+    assert_eq!(counts, [(3, 2, 1), (3, 3, 0)]);
+    assert_eq!(trends[1].pass_rate, Some(1.0));
+
+    let trends: Vec<TrendPoint> = app
+        .get("/api/v1/blend/history/shop/trends?test=Tests.Cart.Add%20Item")
+        .await
+        .json();
+    assert_eq!(trends.len(), 2);
+    assert!(trends.iter().all(|x| (x.total, x.passed) == (1, 1)));
+    assert!(app
+        .get("/api/v1/blend/history/other/trends")
+        .await
+        .json::<Vec<TrendPoint>>()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn compare_runs() {
+    let app = TestApp::new();
+    app.upload("output_a.xml").await;
+    let request = CompareRequest {
+        baseline: CompareInput::Stored(String::from("output_a.xml")),
+        current: CompareInput::Xml(fixture("output_b.xml")),
+        duration_threshold: DEFAULT_DURATION_THRESHOLD,
+    };
+    let response = app.post_json("/api/v1/blend/compare", &request).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let comparison: Comparison = response.json();
+    assert!(comparison.added.is_empty());
+    assert!(comparison.removed.is_empty());
+    assert_eq!(comparison.newly_failing, ["Tests.Login.Invalid Login"]);
+    assert!(comparison.newly_passing.is_empty());
+    assert_eq!(comparison.changed_messages.len(), 1);
+    assert_eq!(comparison.changed_messages[0].baseline, "");
+    assert_eq!(
+        comparison.changed_messages[0].current,
+        "Page should have contained text 'Login failed' but did not."
+    );
+    // 0.325 s before, 5.325 s after
+    assert_eq!(comparison.duration_regressions.len(), 1);
+    let regression = &comparison.duration_regressions[0];
+    assert_eq!(regression.test, "Tests.Login.Invalid Login");
+    assert!((regression.current - regression.baseline - 5.0).abs() < 1e-6);
+
+    let request = CompareRequest {
+        duration_threshold: 10.0,
+        ..request
+    };
+    let comparison: Comparison = app
+        .post_json("/api/v1/blend/compare", &request)
+        .await
+        .json();
+    assert!(comparison.duration_regressions.is_empty());
+
+    // The other way round the test is fixed again.
+    let request = CompareRequest {
+        baseline: request.current,
+        current: request.baseline,
+        ..request
+    };
+    let comparison: Comparison = app
+        .post_json("/api/v1/blend/compare", &request)
+        .await
+        .json();
+    assert!(comparison.newly_failing.is_empty());
+    assert_eq!(comparison.newly_passing, ["Tests.Login.Invalid Login"]);
+}
+
+#[tokio::test]
+async fn report_embeds_spreadsheet() {
+    let app = TestApp::new();
+    app.upload("output_a.xml").await;
+    app.upload("output_b.xml").await;
+
+    let response = app.get("/api/v1/blend/report?include_tags=smoke").await;
+    assert_eq!(response.status, StatusCode::OK);
+    let html = response.text();
+    assert!(html.contains("<a href=\"data:application/vnd.oasis.opendocument.spreadsheet;base64,"));
+    assert!(html.contains("Valid Login"));
+    assert!(!html.contains("Invalid Login"));
+}
+
+#[tokio::test]
+async fn merge_reruns() {
+    let app = TestApp::new();
+    app.upload("output_b.xml").await;
+    app.upload("output_a.xml").await;
+
+    let response = app.get("/api/v1/blend/merge").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.content_type(), "application/xml");
+    let xml = response.text();
+    assert!(xml.contains("schemaversion=\"4\""));
+    assert!(xml.contains("<stat pass=\"3\" fail=\"0\" skip=\"0\">All Tests</stat>"));
+    assert!(xml.contains("<kw name=\"Open Browser\" library=\"SeleniumLibrary\" type=\"SETUP\">"));
+    // Times of the rerun are kept.
+    assert!(xml.contains("starttime=\"20240501 12:00:01.545\" endtime=\"20240501 12:00:01.870\""));
+
+    let response = app.post("/api/v1/blend/xml?format=json", xml).await;
+    let merged: RobotResult = response.json();
+    let tests = merged.tests();
+    assert_eq!(tests.len(), 3);
+    let rerun = tests
+        .iter()
+        .find(|test| test.long_name() == "Tests.Login.Invalid Login")
+        .unwrap();
+    assert_eq!(rerun.test.status, Status::Pass);
+    assert!(rerun
+        .test
+        .message
+        .starts_with("Re-executed test has been merged."));
+    assert!(rerun.test.message.contains("New status: PASS"));
+    assert!(rerun.test.message.contains(
+        "Old status: FAIL\nOld message: Page should have contained text 'Login failed' but did not."
+    ));
+}
+
+/// Upload a rerun of the `Tests` suite of the fixtures.
+async fn upload_rerun(app: &TestApp, name: &str, suite: serde_json::Value) {
+    let response = app
+        .post(
+            &format!("/api/v1/blend/upload/{name}"),
+            json!({ "suite": suite }).to_string(),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+}
+
+async fn merged(app: &TestApp) -> RobotResult {
+    let xml = app.get("/api/v1/blend/merge").await.text();
+    app.post("/api/v1/blend/xml?format=json", xml).await.json()
+}
+
+#[tokio::test]
+async fn merge_ignores_skipped_rerun() {
+    let app = TestApp::new();
+    app.upload("output_b.xml").await;
+    let rerun = json!({"name": "Tests", "suites": [{"name": "Login", "tests": [
+        {"name": "Invalid Login", "status": "SKIP", "message": "Login page down"}
+    ]}]});
+    upload_rerun(&app, "rerun.json", rerun).await;
+
+    let merged = merged(&app).await;
+    let invalid_login = &merged.suite.suites[0].tests[1];
+    assert_eq!(invalid_login.name, "Invalid Login");
+    assert_eq!(invalid_login.status, Status::Fail);
+    assert_eq!(
+        invalid_login.message,
+        "Re-executed test has been merged. Latter result had SKIP status and was ignored. \
+         Message:\nLogin page down\n\n\
+         Original message:\nPage should have contained text 'Login failed' but did not."
+    );
+}
+
+#[tokio::test]
+async fn merge_marks_added_tests_and_suites() {
+    let app = TestApp::new();
+    app.upload("output_a.xml").await;
+    let rerun = json!({"name": "Tests", "suites": [
+        {"name": "Login", "tests": [
+            {"name": "Logout", "status": "FAIL", "message": "No logout link"}
+        ]},
+        {"name": "Search", "tests": [{"name": "Find Item", "status": "PASS"}]}
+    ]});
+    upload_rerun(&app, "rerun.json", rerun).await;
+
+    let merged = merged(&app).await;
+    let login = &merged.suite.suites[0];
+    assert_eq!(login.tests.len(), 3);
+    assert_eq!(
+        login.tests[2].message,
+        "Test added from merged output.\n\nNo logout link"
+    );
+    let search = &merged.suite.suites[2];
+    assert_eq!(search.name, "Search");
+    assert_eq!(search.message, "Suite added from merged output.");
+    // tests of an added suite are taken as they are
+    assert_eq!(search.tests[0].message, "");
+}
+
+#[tokio::test]
+async fn merge_rejects_different_root_suites() {
+    let app = TestApp::new();
+    app.upload("output_a.xml").await;
+    let other = json!({"name": "Other", "tests": [{"name": "Smoke", "status": "PASS"}]});
+    upload_rerun(&app, "other.json", other).await;
+
+    let response = app.get("/api/v1/blend/merge").await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(matches!(response.json(), BlendError::InvalidInput(_)));
+}
+
+#[tokio::test]
+async fn merge_writes_control_structures() {
+    let app = TestApp::new();
+    let output = json!({"suite": {"name": "Loops", "tests": [{
+        "name": "Loop",
+        "status": "PASS",
+        "start": "2024-05-01T12:00:00.000000",
+        "elapsed": 0.5,
+        "body": [
+            {"type": "FOR", "status": "PASS", "body": [
+                {"type": "ITERATION", "status": "PASS", "body": [
+                    {"name": "Log", "owner": "BuiltIn", "status": "PASS"}
+                ]}
+            ]},
+            {"type": "IF/ELSE ROOT", "status": "PASS", "body": [
+                {"type": "IF", "condition": "$x > 1", "status": "PASS"},
+                {"type": "ELSE", "status": "NOT RUN"}
+            ]}
+        ]
+    }]}});
+    let response = app
+        .post("/api/v1/blend/upload/output.json", output.to_string())
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let xml = app.get("/api/v1/blend/merge").await.text();
+    for element in [
+        "<for flavor=\"IN\">\n<iter>\n<kw name=\"Log\" library=\"BuiltIn\">",
+        "<if>\n<branch type=\"IF\" condition=\"$x &gt; 1\">",
+        "<branch type=\"ELSE\">\n<status status=\"NOT RUN\"",
+        "starttime=\"20240501 12:00:00.000\" endtime=\"20240501 12:00:00.500\"",
+    ] {
+        assert!(xml.contains(element), "{element} missing in {xml}");
+    }
+    assert!(!xml.contains("type=\"FOR\""));
+}
+
+#[tokio::test]
+async fn keyword_timing() {
+    let app = TestApp::new();
+    // 19 runs taking 0.1 s on the keyword and one taking 5.1 s
+    for run in 1..20 {
+        app.post(
+            &format!("/api/v1/blend/upload/run_{run:02}.xml"),
+            fixture("output_a.xml"),
+        )
+        .await;
+    }
+    app.upload("output_b.xml").await;
+
+    let response = app.get("/api/v1/blend/timing?top=1").await;
+    assert_eq!(response.status, StatusCode::OK);
+    let timing: Timing = response.json();
+    let keyword = timing
+        .keywords
+        .iter()
+        .find(|keyword| keyword.keyword == "SeleniumLibrary.Page Should Contain")
+        .unwrap();
+    assert_eq!(keyword.calls, 20);
+    assert!((keyword.min - 0.1).abs() < 1e-9);
+    assert!((keyword.max - 5.1).abs() < 1e-9);
+    assert!((keyword.avg - 0.35).abs() < 1e-9);
+    // Nearest rank: the 19th of 20 values, not interpolated towards the maximum.
+    assert!((keyword.p95 - 0.1).abs() < 1e-9);
+    assert_eq!(timing.keywords[0].keyword, "SeleniumLibrary.Open Browser");
+
+    assert_eq!(timing.files.len(), 20);
+    let last = &timing.files[19];
+    assert_eq!(last.file, "output_b.xml");
+    assert_eq!(last.tests.len(), 1);
+    assert_eq!(last.tests[0].name, "Tests.Login.Invalid Login");
+    assert!((last.tests[0].elapsed - 5.325).abs() < 1e-9);
+    assert_eq!(last.keywords.len(), 1);
+    assert!((last.keywords[0].elapsed - 5.1).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn failure_clusters() {
+    let app = TestApp::new();
+    app.upload("output_b.xml").await;
+    let messages = [
+        "Timeout after 5 seconds waiting for /tmp/run-1/page.html \
+         (session 123e4567-e89b-12d3-a456-426614174000)",
+        "Timeout after 30 seconds waiting for /var/tmp/run-22/page.html \
+         (session 9b2f1c3e-0000-4d2a-8f00-1234567890ab)",
+    ];
+    for (run, message) in messages.iter().enumerate() {
+        let xml = fixture("output_b.xml").replace(
+            "Page should have contained text 'Login failed' but did not.",
+            message,
+        );
+        app.post(&format!("/api/v1/blend/upload/timeout_{run}.xml"), xml)
+            .await;
+    }
+
+    let response = app.get("/api/v1/blend/clusters").await;
+    assert_eq!(response.status, StatusCode::OK);
+    let clusters: Vec<FailureCluster> = response.json();
+    assert_eq!(clusters.len(), 2);
+    assert_eq!(
+        clusters[0].pattern,
+        "Timeout after <N> seconds waiting for <PATH> (session <UUID>)"
+    );
+    assert_eq!(clusters[0].count, 2);
+    assert_eq!(clusters[0].samples, messages);
+    let files: Vec<&str> = clusters[0]
+        .tests
+        .iter()
+        .map(|test| test.file.as_str())
+        .collect();
+    assert_eq!(files, ["timeout_0.xml", "timeout_1.xml"]);
+    assert_eq!(clusters[1].count, 1);
+    assert_eq!(clusters[1].tests[0].file, "output_b.xml");
+}
+
+#[tokio::test]
+async fn compare_converts_inline_input() {
+    let app = TestApp::new();
+    app.upload("junit.xml").await;
+    let request = CompareRequest {
+        baseline: CompareInput::Stored(String::from("junit.xml")),
+        current: CompareInput::Xml(fixture("junit.xml")),
+        duration_threshold: DEFAULT_DURATION_THRESHOLD,
+    };
+    let response = app.post_json("/api/v1/blend/compare", &request).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let comparison: Comparison = response.json();
+    assert!(comparison.added.is_empty());
+    assert!(comparison.removed.is_empty());
+    assert!(comparison.newly_failing.is_empty());
+}
+
+#[tokio::test]
+async fn convert_to_json() {
+    let app = TestApp::new();
+    let response = app
+        .post("/api/v1/blend/xml?format=json", fixture("output_b.xml"))
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let result: RobotResult = response.json();
+    let tests = result.tests();
+    assert_eq!(tests.len(), 3);
+    let failed: Vec<String> = tests
+        .iter()
+        .filter(|test| test.test.status == Status::Fail)
+        .map(|test| test.long_name())
+        .collect();
+    assert_eq!(failed, ["Tests.Login.Invalid Login"]);
+}
+
+#[tokio::test]
+async fn convert_with_filter() {
+    let app = TestApp::new();
+    let response = app
+        .post(
+            "/api/v1/blend/xml?format=json&include_tags=smoke",
+            fixture("output_a.xml"),
+        )
+        .await;
+    let result: RobotResult = response.json();
+    let names: Vec<String> = result.tests().iter().map(|test| test.long_name()).collect();
+    assert_eq!(names, ["Tests.Login.Valid Login", "Tests.Cart.Add Item"]);
+}
+
+#[tokio::test]
+async fn convert_with_filter_keeping_all_tests() {
+    let app = TestApp::new();
+    let unfiltered = app
+        .post("/api/v1/blend/xml?format=text", fixture("output_a.xml"))
+        .await;
+    assert_eq!(unfiltered.status, StatusCode::OK);
+    for filter in [
+        "include_suites=Tests",
+        "include_tests=*",
+        "exclude_tags=nightly,wip",
+    ] {
+        let filtered = app
+            .post(
+                &format!("/api/v1/blend/xml?format=text&{filter}"),
+                fixture("output_a.xml"),
+            )
+            .await;
+        assert_eq!(filtered.status, StatusCode::OK, "{filter}");
+        assert_eq!(filtered.body, unfiltered.body, "{filter}");
+    }
+}
+
+#[tokio::test]
+async fn convert_formats() {
+    let app = TestApp::new();
+    for (format, content_type, expected) in [
+        ("csv", "text/csv", "Tests.Login,Invalid Login,FAIL"),
+        (
+            "junit",
+            "application/xml",
+            "<testcase classname=\"Tests.Login\"",
+        ),
+        (
+            "markdown",
+            "text/markdown",
+            "| Tests.Login | Invalid Login | FAIL |",
+        ),
+    ] {
+        let response = app
+            .post(
+                &format!("/api/v1/blend/xml?format={format}"),
+                fixture("output_b.xml"),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{format}");
+        assert_eq!(response.content_type(), content_type, "{format}");
+        assert!(response.text().contains(expected), "{format}");
+    }
+
+    let response = app.post("/api/v1/blend/xml", fixture("output_b.xml")).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.body.is_empty());
+}
+
+async fn convert_accepting(app: &TestApp, accept: &str) -> TestResponse {
+    let request = request(Method::POST, "/api/v1/blend/xml")
+        .header(header::ACCEPT, accept)
+        .body(Body::from(fixture("output_a.xml")))
+        .unwrap();
+    app.send(request).await
+}
+
+#[tokio::test]
+async fn convert_format_from_accept() {
+    let app = TestApp::new();
+    for (accept, content_type) in [
+        ("text/csv;q=0.9, application/json", "application/json"),
+        ("text/csv, application/json", "text/csv"),
+        ("application/json;q=0.5, text/*;q=0.8", "text/plain"),
+        ("text/*, text/markdown;q=0.2, text/plain;q=0.1", "text/csv"),
+        ("*/*;q=0.1, text/markdown", "text/markdown"),
+        ("*/*", "text/plain"),
+        ("text/plain;q=0, */*", "application/json"),
+        ("TEXT/CSV;Q=1", "text/csv"),
+        ("image/png, application/junit+xml;q=0.3", "application/xml"),
+    ] {
+        let response = convert_accepting(&app, accept).await;
+        assert_eq!(response.status, StatusCode::OK, "{accept}");
+        assert!(
+            response.content_type().starts_with(content_type),
+            "{accept}"
+        );
+    }
+
+    for accept in ["image/png", "text/csv;q=0, application/*;q=0", "*/*;q=0"] {
+        let response = convert_accepting(&app, accept).await;
+        assert_eq!(response.status, StatusCode::NOT_ACCEPTABLE, "{accept}");
+        assert!(matches!(response.json(), BlendError::NotAcceptable(_)));
+    }
+
+    let request = request(Method::POST, "/api/v1/blend/xml?format=csv")
+        .header(header::ACCEPT, "image/png")
+        .body(Body::from(fixture("output_a.xml")))
+        .unwrap();
+    assert_eq!(app.send(request).await.content_type(), "text/csv");
+}
+
+#[tokio::test]
+async fn convert_junit_input() {
+    let app = TestApp::new();
+    let response = app
+        .post("/api/v1/blend/xml?format=json", fixture("junit.xml"))
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let result: RobotResult = response.json();
+    assert_eq!(result.tests().len(), 2);
+}
+
+#[tokio::test]
+async fn convert_rejects_unreadable_input() {
+    let app = TestApp::new();
+    let response = app
+        .post("/api/v1/blend/xml?format=json", "<robot><suite>")
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(matches!(response.json(), BlendError::InvalidInput(_)));
+}
+
+#[tokio::test]
+async fn missing_api_key() {
+    let app = TestApp::new();
+    for (method, uri) in [
+        (Method::GET, "/api/v1/blend/list"),
+        (Method::DELETE, "/api/v1/blend/list"),
+        (Method::GET, "/api/v1/blend/blend"),
+        (Method::POST, "/api/v1/blend/upload/output_a.xml"),
+        (Method::POST, "/api/v1/blend/xml"),
+    ] {
+        let request = Request::builder()
+            .method(method.clone())
+            .uri(uri)
+            .body(Body::from(fixture("output_a.xml")))
+            .unwrap();
+        let response = app.send(request).await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED, "{method} {uri}");
+        assert!(matches!(response.json(), BlendError::Unauthorized(_)));
+    }
+    assert!(list(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn wrong_api_key() {
+    let app = TestApp::new();
+    let request = Request::builder()
+        .uri("/api/v1/blend/list")
+        .header("theapikey", format!("not-{API_KEY}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.send(request).await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    match response.json() {
+        BlendError::Unauthorized(message) => assert_eq!(message, "incorrect api key"),
+        other => panic!("unexpected error {other:?}"),
+    }
+}
@@ -0,0 +1,183 @@
+//! The typed client against the router served on a local port
+mod common;
+
+use axum::http::StatusCode;
+use axum_test_client::blend::{BlendError, BlendRequest, RecordLabels};
+use axum_test_client::filter::Filter;
+use axum_test_client::todo::{TodoError, TodoPatch, MAX_TODO_PAGE};
+use axum_test_client::{Client, ClientBuilder, Error};
+use axum_test_server::todo_store::{MemoryTodos, TodoRepository};
+use axum_test_server::{blend_api::BlendState, todo_api::TodoState, ApiBuilder, Auth};
+use common::{fixture, API_KEY};
+
+/// Serve a fresh router with the apis below `prefix`, returns the server url.
+async fn serve(prefix: &str, todos: TodoState) -> String {
+    let (router, _) = ApiBuilder::new()
+        .prefix(prefix)
+        .blend_state(BlendState::open(":memory:").unwrap())
+        .todo_state(todos)
+        .auth(Auth::ApiKey(String::from(API_KEY)))
+        .build()
+        .unwrap()
+        .split_for_parts();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    format!("http://{address}")
+}
+
+fn client(url: &str) -> Client {
+    ClientBuilder::new(url).api_key(API_KEY).build().unwrap()
+}
+
+/// Status and body of a failed request.
+fn error_status<T: std::fmt::Debug>(result: Result<T, Error>) -> (StatusCode, String) {
+    match result {
+        Err(Error::Status { status, body }) => (status, body),
+        other => panic!("no error status: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn path_segments_are_encoded() {
+    let client = client(&serve("/api/v1", TodoState::new()).await);
+    let blend = client.blend();
+    let name = "run 1/output?#%.xml";
+    blend.upload(name, fixture("output_a.xml")).await.unwrap();
+    assert_eq!(blend.list().await.unwrap(), [name]);
+
+    let request = BlendRequest {
+        record: Some(RecordLabels {
+            project: String::from("web shop/v2"),
+            branch: String::from("feature/a&b"),
+            timestamp: Some(String::from("2025-05-01T12:00:00Z")),
+        }),
+        ..BlendRequest::default()
+    };
+    blend.blend(&request, false).await.unwrap();
+    let outcomes = blend
+        .test_history(
+            "web shop/v2",
+            "Tests.Login.Valid Login",
+            Some("feature/a&b"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(outcomes[0].run, name);
+    let trends = blend
+        .trends("web shop/v2", None, Some("Tests.Cart.Add Item"))
+        .await
+        .unwrap();
+    assert_eq!(trends.len(), 1);
+    assert_eq!(trends[0].total, 1);
+}
+
+#[tokio::test]
+async fn base_url_with_path() {
+    let url = serve("/ci/api/v1", TodoState::new()).await;
+    for base_url in [format!("{url}/ci"), format!("{url}/ci/")] {
+        let client = client(&base_url);
+        let todo = client.todo();
+        let created = todo.create("Buy milk").await.unwrap();
+        let last = todo.list_all().await.unwrap().pop().unwrap();
+        assert_eq!((last.id, last.value), (created.id, created.value));
+    }
+    let (status, _) = error_status(client(&url).todo().list_all().await);
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    for base_url in ["localhost:44001", "mailto:blend@example.com", "no url"] {
+        assert!(matches!(
+            ClientBuilder::new(base_url).build(),
+            Err(Error::BaseUrl(_))
+        ));
+    }
+}
+
+#[tokio::test]
+async fn filter_query_is_encoded() {
+    let client = client(&serve("/api/v1", TodoState::new()).await);
+    let blend = client.blend();
+    // unencoded, `#` would cut off the patterns following it and `&` start a parameter
+    let filter = Filter {
+        exclude_suites: vec![String::from("Other & #1"), String::from("a=b")],
+        include_tests: vec![String::from("Valid Login"), String::from("Add*")],
+        exclude_tags: vec![String::from("100%")],
+        ..Filter::default()
+    };
+    let result = blend.parse(fixture("output_a.xml"), &filter).await.unwrap();
+    let names: Vec<String> = result.tests().iter().map(|test| test.long_name()).collect();
+    assert_eq!(names, ["Tests.Login.Valid Login", "Tests.Cart.Add Item"]);
+
+    blend
+        .upload("output_a.xml", fixture("output_a.xml"))
+        .await
+        .unwrap();
+    let timing = blend.timing(1, &filter).await.unwrap();
+    assert_eq!(timing.files.len(), 1);
+    let merged = blend.merge(&filter).await.unwrap();
+    assert!(merged.contains("Valid Login"));
+    assert!(!merged.contains("Invalid Login"));
+}
+
+#[tokio::test]
+async fn error_status_bodies() {
+    let url = serve("/api/v1", TodoState::new()).await;
+    let (status, body) = error_status(
+        ClientBuilder::new(&url)
+            .api_key("wrong")
+            .build()
+            .unwrap()
+            .blend()
+            .list()
+            .await,
+    );
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(matches!(
+        serde_json::from_str(&body).unwrap(),
+        BlendError::Unauthorized(_)
+    ));
+
+    let client = client(&url);
+    let (status, body) = error_status(client.blend().upload("page.html", "<html/>").await);
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(matches!(
+        serde_json::from_str(&body).unwrap(),
+        BlendError::InvalidInput(_)
+    ));
+
+    let error = client
+        .todo()
+        .update(99, &TodoPatch::default())
+        .await
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("server answered 404 Not Found: "));
+    let (status, body) = error_status::<()>(Err(error));
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(matches!(
+        serde_json::from_str(&body).unwrap(),
+        TodoError::NotFound(id) if id == "id = 99"
+    ));
+}
+
+#[tokio::test]
+async fn list_all_follows_next_links() {
+    let mut todos = MemoryTodos::default();
+    for index in 0..2 * MAX_TODO_PAGE + 3 {
+        todos.create(&format!("Todo {index}")).unwrap();
+    }
+    let url = serve("/ci/api/v1", TodoState::with_repository(todos)).await;
+    let client = client(&format!("{url}/ci"));
+    let todo = client.todo();
+    for id in [1, MAX_TODO_PAGE as i32, MAX_TODO_PAGE as i32 + 1] {
+        todo.delete(id).await.unwrap();
+    }
+    let all = todo.list_all().await.unwrap();
+    let ids: Vec<i32> = all.iter().map(|todo| todo.id).collect();
+    let expected: Vec<i32> = (2..=2 * MAX_TODO_PAGE as i32 + 3)
+        .filter(|id| ![MAX_TODO_PAGE as i32, MAX_TODO_PAGE as i32 + 1].contains(id))
+        .collect();
+    assert_eq!(ids, expected);
+}
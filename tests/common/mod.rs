@@ -0,0 +1,150 @@
+//! In-process server for the integration tests
+//!
+//! The router is the one `main.rs` serves, with in-memory history and todo
+//! databases and a fixed api key. Requests go through `tower::ServiceExt::oneshot`.
+#![allow(dead_code)]
+
+use axum::body::{Body, Bytes};
+use axum::http::{header, HeaderMap, Method, Request, StatusCode};
+use axum::Router;
+use axum_test_server::{
+    blend_api::BlendState, todo_api::TodoState, ApiBuilder, Auth, API_KEY_HEADER,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+use tower::ServiceExt;
+
+pub const API_KEY: &str = "test-key";
+
+/// Upper limit for response bodies read by the tests
+const BODY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Content of a file in `tests/fixtures`.
+pub fn fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name);
+    std::fs::read_to_string(&path).unwrap_or_else(|error| panic!("{}: {error}", path.display()))
+}
+
+/// Request with the api key set.
+pub fn request(method: Method, uri: &str) -> axum::http::request::Builder {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(API_KEY_HEADER, API_KEY)
+}
+
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl TestResponse {
+    pub fn text(&self) -> String {
+        String::from_utf8(self.body.to_vec()).expect("body is not utf-8")
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> T {
+        serde_json::from_slice(&self.body)
+            .unwrap_or_else(|error| panic!("{error}: {}", String::from_utf8_lossy(&self.body)))
+    }
+
+    pub fn content_type(&self) -> &str {
+        self.headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+    }
+}
+
+/// A fresh server, state is not shared between instances.
+pub struct TestApp {
+    router: Router,
+}
+
+impl TestApp {
+    pub fn new() -> Self {
+        Self::with_todos(TodoState::open(":memory:").expect("in-memory todos"))
+    }
+
+    /// A fresh server on the todos of `state`.
+    pub fn with_todos(state: TodoState) -> Self {
+        let (router, _) = ApiBuilder::new()
+            .blend_state(BlendState::open(":memory:").expect("in-memory history"))
+            .todo_state(state)
+            .auth(Auth::ApiKey(String::from(API_KEY)))
+            .build()
+            .expect("router")
+            .split_for_parts();
+        TestApp { router }
+    }
+
+    pub async fn send(&self, request: Request<Body>) -> TestResponse {
+        let response = self
+            .router
+            .clone()
+            .oneshot(request)
+            .await
+            .expect("infallible");
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = axum::body::to_bytes(response.into_body(), BODY_LIMIT)
+            .await
+            .expect("response body");
+        TestResponse {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    pub async fn get(&self, uri: &str) -> TestResponse {
+        self.send(request(Method::GET, uri).body(Body::empty()).unwrap())
+            .await
+    }
+
+    pub async fn post(&self, uri: &str, body: impl Into<Body>) -> TestResponse {
+        self.send(request(Method::POST, uri).body(body.into()).unwrap())
+            .await
+    }
+
+    pub async fn send_json(
+        &self,
+        method: Method,
+        uri: &str,
+        value: &impl Serialize,
+    ) -> TestResponse {
+        let request = request(method, uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(serde_json::to_vec(value).unwrap()))
+            .unwrap();
+        self.send(request).await
+    }
+
+    pub async fn post_json(&self, uri: &str, value: &impl Serialize) -> TestResponse {
+        self.send_json(Method::POST, uri, value).await
+    }
+
+    pub async fn put_json(&self, uri: &str, value: &impl Serialize) -> TestResponse {
+        self.send_json(Method::PUT, uri, value).await
+    }
+
+    pub async fn patch_json(&self, uri: &str, value: &impl Serialize) -> TestResponse {
+        self.send_json(Method::PATCH, uri, value).await
+    }
+
+    pub async fn delete(&self, uri: &str) -> TestResponse {
+        self.send(request(Method::DELETE, uri).body(Body::empty()).unwrap())
+            .await
+    }
+
+    /// Upload a fixture under its file name.
+    pub async fn upload(&self, name: &str) -> TestResponse {
+        self.post(&format!("/api/v1/blend/upload/{name}"), fixture(name))
+            .await
+    }
+}
@@ -0,0 +1,99 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum_test_server::docs::{Docs, OPENAPI_PATH};
+use tower::ServiceExt;
+
+async fn status(docs: &Docs, uri: &str) -> StatusCode {
+    let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+    docs.router(axum_test_server::openapi())
+        .oneshot(request)
+        .await
+        .expect("infallible")
+        .status()
+}
+
+#[tokio::test]
+async fn swagger_ui_on_configured_path() {
+    let docs = Docs {
+        swagger_ui: String::from("/docs"),
+        ..Docs::default()
+    };
+    assert_eq!(status(&docs, "/docs/").await, StatusCode::OK);
+    assert_eq!(status(&docs, "/swagger-ui/").await, StatusCode::NOT_FOUND);
+    assert_eq!(status(&docs, OPENAPI_PATH).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn disabled_keeps_openapi_document() {
+    let docs = Docs {
+        enabled: false,
+        ..Docs::default()
+    };
+    assert_eq!(status(&docs, "/swagger-ui/").await, StatusCode::NOT_FOUND);
+    assert_eq!(status(&docs, OPENAPI_PATH).await, StatusCode::OK);
+}
+
+#[cfg(feature = "redoc")]
+#[tokio::test]
+async fn redoc_on_configured_path() {
+    assert_eq!(status(&Docs::default(), "/redoc").await, StatusCode::OK);
+    let docs = Docs {
+        redoc: String::from("/docs/redoc"),
+        ..Docs::default()
+    };
+    assert_eq!(status(&docs, "/docs/redoc").await, StatusCode::OK);
+    assert_eq!(status(&docs, "/redoc").await, StatusCode::NOT_FOUND);
+    let disabled = Docs {
+        enabled: false,
+        ..Docs::default()
+    };
+    assert_eq!(status(&disabled, "/redoc").await, StatusCode::NOT_FOUND);
+}
+
+#[cfg(feature = "rapidoc")]
+#[tokio::test]
+async fn rapidoc_on_configured_path() {
+    assert_eq!(status(&Docs::default(), "/rapidoc").await, StatusCode::OK);
+    let docs = Docs {
+        rapidoc: String::from("/docs/rapidoc"),
+        ..Docs::default()
+    };
+    let request = Request::builder()
+        .uri("/docs/rapidoc")
+        .body(Body::empty())
+        .unwrap();
+    let response = docs
+        .router(axum_test_server::openapi())
+        .oneshot(request)
+        .await
+        .expect("infallible");
+    assert_eq!(response.status(), StatusCode::OK);
+    let page = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    // RapiDoc loads the document served next to it
+    assert!(String::from_utf8_lossy(&page).contains(OPENAPI_PATH));
+    assert_eq!(status(&docs, "/rapidoc").await, StatusCode::NOT_FOUND);
+    let disabled = Docs {
+        enabled: false,
+        ..Docs::default()
+    };
+    assert_eq!(status(&disabled, "/rapidoc").await, StatusCode::NOT_FOUND);
+}
+
+#[cfg(feature = "scalar")]
+#[tokio::test]
+async fn scalar_on_configured_path() {
+    assert_eq!(status(&Docs::default(), "/scalar").await, StatusCode::OK);
+    let docs = Docs {
+        scalar: String::from("/docs/scalar"),
+        ..Docs::default()
+    };
+    assert_eq!(status(&docs, "/docs/scalar").await, StatusCode::OK);
+    assert_eq!(status(&docs, "/scalar").await, StatusCode::NOT_FOUND);
+    let disabled = Docs {
+        enabled: false,
+        ..Docs::default()
+    };
+    assert_eq!(status(&disabled, "/scalar").await, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,529 @@
+//! Contract tests of the OpenAPI document against the routes
+//!
+//! Every exchange is checked against the responses its operation declares: the
+//! status has to be listed, the content type has to match and json bodies have
+//! to validate against the declared schema.
+mod common;
+
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use common::{fixture, request, TestApp, TestResponse};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+
+const BLEND: &str = "/api/v1/blend";
+const TODO: &str = "/api/v1/todo";
+const STUFF: &str = "/api/v1/stuff";
+
+struct Contract {
+    app: TestApp,
+    spec: Value,
+    covered: BTreeSet<(String, String)>,
+}
+
+impl Contract {
+    fn new() -> Self {
+        Contract {
+            app: TestApp::new(),
+            spec: serde_json::to_value(axum_test_server::openapi()).unwrap(),
+            covered: BTreeSet::new(),
+        }
+    }
+
+    /// Send `request` to the operation at `path`, expecting `status`.
+    async fn check(&mut self, path: &str, request: Request<Body>, status: StatusCode) {
+        let method = request.method().as_str().to_lowercase();
+        let uri = request.uri().to_string();
+        let response = self.app.send(request).await;
+        let operation = format!("{method} {path} ({uri})");
+        assert_eq!(
+            response.status,
+            status,
+            "{operation}: {}",
+            String::from_utf8_lossy(&response.body)
+        );
+        let declared = &self.spec["paths"][path][&method]["responses"][response.status.as_str()];
+        assert!(
+            declared.is_object(),
+            "{operation}: status {} is not declared",
+            response.status
+        );
+        if let Err(error) = self.check_body(declared, &response) {
+            panic!("{operation}: {error}");
+        }
+        self.covered.insert((method, String::from(path)));
+    }
+
+    async fn get(&mut self, path: &str, uri: &str, status: StatusCode) {
+        let request = request(Method::GET, uri).body(Body::empty()).unwrap();
+        self.check(path, request, status).await
+    }
+
+    async fn post(&mut self, path: &str, uri: &str, body: impl Into<Body>, status: StatusCode) {
+        let request = request(Method::POST, uri).body(body.into()).unwrap();
+        self.check(path, request, status).await
+    }
+
+    async fn post_json(&mut self, path: &str, uri: &str, value: Value, status: StatusCode) {
+        self.send_json(path, Method::POST, uri, value, status).await
+    }
+
+    async fn send_json(
+        &mut self,
+        path: &str,
+        method: Method,
+        uri: &str,
+        value: Value,
+        status: StatusCode,
+    ) {
+        let request = request(method, uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(value.to_string()))
+            .unwrap();
+        self.check(path, request, status).await
+    }
+
+    async fn send(&mut self, path: &str, method: Method, uri: &str, status: StatusCode) {
+        let request = request(method, uri).body(Body::empty()).unwrap();
+        self.check(path, request, status).await
+    }
+
+    /// Request without api key.
+    async fn anonymous(&mut self, path: &str, method: Method, uri: &str, status: StatusCode) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        self.check(path, request, status).await
+    }
+
+    fn check_body(&self, declared: &Value, response: &TestResponse) -> Result<(), String> {
+        let content = match declared["content"].as_object() {
+            Some(content) if !content.is_empty() => content,
+            _ if response.body.is_empty() => return Ok(()),
+            _ => return Err(String::from("body returned, none declared")),
+        };
+        let media_type = response
+            .content_type()
+            .split(';')
+            .next()
+            .unwrap_or_default();
+        let Some(media) = content.get(media_type) else {
+            return Err(format!(
+                "content type {media_type:?} not declared, expected one of {:?}",
+                content.keys().collect::<Vec<_>>()
+            ));
+        };
+        if media_type != "application/json" {
+            return Ok(());
+        }
+        let body: Value = serde_json::from_slice(&response.body)
+            .map_err(|error| format!("body is not json: {error}"))?;
+        self.validate(&media["schema"], &body, "body")
+    }
+
+    /// Check `value` against the subset of JSON schema utoipa generates.
+    fn validate(&self, schema: &Value, value: &Value, at: &str) -> Result<(), String> {
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.trim_start_matches("#/components/schemas/");
+            let schema = &self.spec["components"]["schemas"][name];
+            if schema.is_null() {
+                return Err(format!("{at}: unknown schema {reference}"));
+            }
+            return self.validate(schema, value, at);
+        }
+        if let Some(schemas) = schema["allOf"].as_array() {
+            for schema in schemas {
+                self.validate(schema, value, at)?;
+            }
+        }
+        for key in ["oneOf", "anyOf"] {
+            if let Some(schemas) = schema[key].as_array() {
+                if !schemas
+                    .iter()
+                    .any(|schema| self.validate(schema, value, at).is_ok())
+                {
+                    return Err(format!("{at}: {value} matches none of {key}"));
+                }
+            }
+        }
+        if let Some(values) = schema["enum"].as_array() {
+            if !values.contains(value) {
+                return Err(format!("{at}: {value} is not one of {values:?}"));
+            }
+        }
+        let types: Vec<&str> = match &schema["type"] {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|name| has_type(value, name)) {
+            return Err(format!("{at}: {value} is not of type {types:?}"));
+        }
+        if let Value::Object(object) = value {
+            for name in schema["required"].as_array().into_iter().flatten() {
+                let name = name.as_str().unwrap_or_default();
+                if !object.contains_key(name) {
+                    return Err(format!("{at}: missing {name}"));
+                }
+            }
+            for (name, field) in object {
+                let at = format!("{at}.{name}");
+                match schema["properties"].get(name) {
+                    Some(property) => self.validate(property, field, &at)?,
+                    None => match &schema["additionalProperties"] {
+                        Value::Bool(false) => return Err(format!("{at}: not declared")),
+                        Value::Object(_) => {
+                            self.validate(&schema["additionalProperties"], field, &at)?
+                        }
+                        _ => (),
+                    },
+                }
+            }
+        }
+        if let (Value::Array(items), Some(item)) = (value, schema.get("items")) {
+            for (index, value) in items.iter().enumerate() {
+                self.validate(item, value, &format!("{at}[{index}]"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Operations of the document no exchange was checked against.
+    fn uncovered(&self) -> Vec<String> {
+        let mut uncovered = Vec::new();
+        for (path, operations) in self.spec["paths"].as_object().unwrap() {
+            for method in operations.as_object().unwrap().keys() {
+                if !self.covered.contains(&(method.clone(), path.clone())) {
+                    uncovered.push(format!("{method} {path}"));
+                }
+            }
+        }
+        uncovered
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+#[tokio::test]
+async fn responses_match_spec() {
+    use StatusCode as S;
+    let mut c = Contract::new();
+
+    let xml = &format!("{BLEND}/xml");
+    c.post(
+        xml,
+        &format!("{xml}?format=json"),
+        fixture("output_b.xml"),
+        S::OK,
+    )
+    .await;
+    c.post(
+        xml,
+        &format!("{xml}?format=csv"),
+        fixture("output_b.xml"),
+        S::OK,
+    )
+    .await;
+    c.post(
+        xml,
+        &format!("{xml}?format=json"),
+        "<robot><suite>",
+        S::BAD_REQUEST,
+    )
+    .await;
+    let refused = request(Method::POST, xml)
+        .header(header::ACCEPT, "image/png")
+        .body(Body::from(fixture("output_b.xml")))
+        .unwrap();
+    c.check(xml, refused, S::NOT_ACCEPTABLE).await;
+    c.anonymous(xml, Method::POST, xml, S::UNAUTHORIZED).await;
+
+    let upload = &format!("{BLEND}/upload/{{name}}");
+    for name in ["output_a.xml", "output_b.xml"] {
+        c.post(
+            upload,
+            &format!("{BLEND}/upload/{name}"),
+            fixture(name),
+            S::OK,
+        )
+        .await;
+    }
+    c.post(
+        upload,
+        &format!("{BLEND}/upload/output_a.xml"),
+        fixture("output_a.xml"),
+        S::CONFLICT,
+    )
+    .await;
+    c.post(
+        upload,
+        &format!("{BLEND}/upload/broken.json"),
+        "{",
+        S::BAD_REQUEST,
+    )
+    .await;
+    c.anonymous(
+        upload,
+        Method::POST,
+        &format!("{BLEND}/upload/a.xml"),
+        S::UNAUTHORIZED,
+    )
+    .await;
+
+    let list = &format!("{BLEND}/list");
+    c.get(list, list, S::OK).await;
+    c.anonymous(list, Method::GET, list, S::UNAUTHORIZED).await;
+
+    let blend = &format!("{BLEND}/blend");
+    c.get(blend, blend, S::OK).await;
+    c.anonymous(blend, Method::GET, blend, S::UNAUTHORIZED)
+        .await;
+    let record = json!({"record": {"project": "shop", "branch": "main"}});
+    c.post_json(blend, blend, record, S::OK).await;
+    let yesterday =
+        json!({"record": {"project": "shop", "branch": "main", "timestamp": "yesterday"}});
+    c.post_json(blend, blend, yesterday, S::BAD_REQUEST).await;
+    let missing = json!({"files": [{"name": "missing.xml"}]});
+    c.post_json(blend, blend, missing.clone(), S::NOT_FOUND)
+        .await;
+    let duplicate = json!({"files": [
+        {"name": "output_a.xml", "display_name": "Run"},
+        {"name": "output_b.xml", "display_name": "Run"}
+    ]});
+    c.post_json(blend, blend, duplicate.clone(), S::CONFLICT)
+        .await;
+
+    let summary = &format!("{BLEND}/summary");
+    c.get(summary, summary, S::OK).await;
+    c.post_json(summary, summary, json!({}), S::OK).await;
+    c.post_json(summary, summary, missing, S::NOT_FOUND).await;
+    c.post_json(summary, summary, duplicate, S::CONFLICT).await;
+
+    for path in ["flaky", "timing", "clusters", "report", "merge"] {
+        let path = &format!("{BLEND}/{path}");
+        c.get(path, path, S::OK).await;
+        c.anonymous(path, Method::GET, path, S::UNAUTHORIZED).await;
+    }
+
+    let compare = json!({
+        "baseline": {"stored": "output_a.xml"},
+        "current": {"stored": "output_b.xml"}
+    });
+    let unknown = json!({
+        "baseline": {"stored": "output_a.xml"},
+        "current": {"stored": "missing.xml"}
+    });
+    let broken = json!({
+        "baseline": {"stored": "output_a.xml"},
+        "current": {"xml": "<robot><suite>"}
+    });
+    for path in ["compare", "compare/report"] {
+        let path = &format!("{BLEND}/{path}");
+        c.post_json(path, path, compare.clone(), S::OK).await;
+        c.post_json(path, path, unknown.clone(), S::NOT_FOUND).await;
+        c.post_json(path, path, broken.clone(), S::BAD_REQUEST)
+            .await;
+    }
+
+    // Rejected queries and bodies are answered with the declared 400.
+    c.post(
+        xml,
+        &format!("{xml}?format=pdf"),
+        fixture("output_b.xml"),
+        S::BAD_REQUEST,
+    )
+    .await;
+    c.post(
+        upload,
+        &format!("{BLEND}/upload/latin1.xml"),
+        vec![0xff_u8, 0xfe],
+        S::BAD_REQUEST,
+    )
+    .await;
+    let consume = &format!("{blend}?consume=maybe");
+    c.get(blend, consume, S::BAD_REQUEST).await;
+    c.post_json(blend, consume, json!({}), S::BAD_REQUEST).await;
+    for path in [blend, summary] {
+        c.post_json(path, path, json!({"depth": "deep"}), S::BAD_REQUEST)
+            .await;
+        // without content type
+        c.post(path, path, "{}", S::BAD_REQUEST).await;
+    }
+    for path in ["summary", "timing", "clusters", "report", "merge"] {
+        let path = &format!("{BLEND}/{path}");
+        let uri = format!("{path}?include_tags=smoke&include_tags=critical");
+        c.get(path, &uri, S::BAD_REQUEST).await;
+    }
+    let timing = &format!("{BLEND}/timing");
+    c.get(timing, &format!("{timing}?top=-1"), S::BAD_REQUEST)
+        .await;
+    for path in ["compare", "compare/report"] {
+        let path = &format!("{BLEND}/{path}");
+        let incomplete = json!({"baseline": {"stored": "output_a.xml"}});
+        c.post_json(path, path, incomplete, S::BAD_REQUEST).await;
+        c.post(path, path, compare.to_string(), S::BAD_REQUEST)
+            .await;
+    }
+
+    let history = &format!("{BLEND}/history/{{project}}/tests/{{test}}");
+    let uri = format!("{BLEND}/history/shop/tests/Tests.Login.Invalid%20Login");
+    c.get(history, &uri, S::OK).await;
+    c.anonymous(history, Method::GET, &uri, S::UNAUTHORIZED)
+        .await;
+    let trends = &format!("{BLEND}/history/{{project}}/trends");
+    c.get(trends, &format!("{BLEND}/history/shop/trends"), S::OK)
+        .await;
+
+    c.post(
+        upload,
+        &format!("{BLEND}/upload/page.html"),
+        "<html/>",
+        S::BAD_REQUEST,
+    )
+    .await;
+    // Robot results are stored as they are and fail once parsed.
+    c.post(
+        upload,
+        &format!("{BLEND}/upload/broken.xml"),
+        "<robot/>",
+        S::OK,
+    )
+    .await;
+    c.get(
+        blend,
+        &format!("{blend}?include_tags=smoke"),
+        S::BAD_REQUEST,
+    )
+    .await;
+    for path in ["summary", "flaky", "timing", "clusters", "report", "merge"] {
+        let path = &format!("{BLEND}/{path}");
+        c.get(path, path, S::BAD_REQUEST).await;
+    }
+
+    c.send(list, Method::DELETE, list, S::OK).await;
+    c.anonymous(list, Method::DELETE, list, S::UNAUTHORIZED)
+        .await;
+    let merge = &format!("{BLEND}/merge");
+    c.get(merge, merge, S::NOT_FOUND).await;
+
+    c.post_json(TODO, TODO, json!({"value": "Buy milk"}), S::CREATED)
+        .await;
+    c.get(TODO, TODO, S::OK).await;
+    c.get(TODO, &format!("{TODO}?cursor=0&limit=1"), S::OK)
+        .await;
+    c.get(TODO, &format!("{TODO}?cursor=first"), S::BAD_REQUEST)
+        .await;
+    let search = &format!("{TODO}/search");
+    c.get(search, &format!("{search}?value=milk&done=false"), S::OK)
+        .await;
+    let regex = format!("{search}?value=(&match=regex");
+    c.get(search, &regex, S::BAD_REQUEST).await;
+    let id = &format!("{TODO}/{{id}}");
+    let (one, two) = (&format!("{TODO}/1"), &format!("{TODO}/2"));
+    let done = json!({"done": true}).to_string();
+    let wrong_key = Request::builder()
+        .method(Method::PATCH)
+        .uri(format!("{TODO}/1"))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(axum_test_server::API_KEY_HEADER, "wrong")
+        .body(Body::from(done))
+        .unwrap();
+    c.check(id, wrong_key, S::UNAUTHORIZED).await;
+    let update = json!({"value": "Buy oat milk", "done": false});
+    c.send_json(id, Method::PUT, one, update.clone(), S::OK)
+        .await;
+    c.send_json(id, Method::PUT, two, update, S::NOT_FOUND)
+        .await;
+    c.send_json(id, Method::PUT, one, json!({}), S::UNPROCESSABLE_ENTITY)
+        .await;
+    let patch = json!({"done": true});
+    c.send_json(id, Method::PATCH, one, patch.clone(), S::OK)
+        .await;
+    c.send_json(id, Method::PATCH, two, patch, S::NOT_FOUND)
+        .await;
+    let empty = json!({"value": ""});
+    c.send_json(id, Method::PATCH, one, empty, S::UNPROCESSABLE_ENTITY)
+        .await;
+    c.anonymous(id, Method::DELETE, one, S::UNAUTHORIZED).await;
+    c.send(id, Method::DELETE, one, S::OK).await;
+    c.send(id, Method::DELETE, one, S::NOT_FOUND).await;
+
+    let stuff = &format!("{STUFF}/stuff/{{mul}}");
+    c.get(stuff, &format!("{STUFF}/stuff/2"), S::OK).await;
+    c.get(stuff, &format!("{STUFF}/stuff/many"), S::BAD_REQUEST)
+        .await;
+    c.anonymous(
+        stuff,
+        Method::GET,
+        &format!("{STUFF}/stuff/2"),
+        S::UNAUTHORIZED,
+    )
+    .await;
+    let testquery = &format!("{STUFF}/stuff/testquery");
+    c.get(testquery, &format!("{testquery}?name=x"), S::OK)
+        .await;
+    c.get(testquery, testquery, S::BAD_REQUEST).await;
+
+    assert_eq!(c.uncovered(), Vec::<String>::new());
+}
+
+/// References of `value` that are not in the components of `spec`.
+fn dangling_refs(spec: &Value, value: &Value, dangling: &mut Vec<String>) {
+    match value {
+        Value::Object(object) => {
+            if let Some(reference) = object.get("$ref").and_then(Value::as_str) {
+                let name = reference.trim_start_matches("#/components/schemas/");
+                if spec["components"]["schemas"][name].is_null() {
+                    dangling.push(String::from(reference));
+                }
+            }
+            for value in object.values() {
+                dangling_refs(spec, value, dangling);
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                dangling_refs(spec, value, dangling);
+            }
+        }
+        _ => (),
+    }
+}
+
+#[test]
+fn schema_refs_resolve() {
+    let spec = serde_json::to_value(axum_test_server::openapi()).unwrap();
+    let mut dangling = Vec::new();
+    dangling_refs(&spec, &spec, &mut dangling);
+    assert_eq!(dangling, Vec::<String>::new());
+}
+
+#[test]
+fn export_command_writes_spec() {
+    let path = std::env::temp_dir().join(format!("openapi-{}.json", std::process::id()));
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_axum-test-server"))
+        .arg("openapi")
+        .arg(&path)
+        .status()
+        .expect("run axum-test-server");
+    assert!(status.success());
+    let written: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        written,
+        serde_json::to_value(axum_test_server::openapi()).unwrap()
+    );
+}
@@ -0,0 +1,26 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum_test_client::stuff::StuffError;
+use common::TestApp;
+
+#[tokio::test]
+async fn stuff() {
+    let app = TestApp::new();
+    let response = app.get("/api/v1/stuff/stuff/3").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json::<String>(), "StuffStuffStuff");
+}
+
+#[tokio::test]
+async fn stuff_requires_api_key() {
+    let app = TestApp::new();
+    let request = Request::builder()
+        .uri("/api/v1/stuff/stuff/3")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.send(request).await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert!(matches!(response.json(), StuffError::Unauthorized(_)));
+}
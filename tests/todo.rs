@@ -0,0 +1,383 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use axum_test_client::todo::{NewTodo, Todo, TodoError, TodoUpdate};
+use axum_test_server::todo_api::{TodoState, TOTAL_COUNT_HEADER};
+use common::TestApp;
+use serde_json::json;
+
+fn todo(value: &str) -> NewTodo {
+    NewTodo {
+        value: String::from(value),
+    }
+}
+
+async fn list(app: &TestApp) -> Vec<Todo> {
+    let response = app.get("/api/v1/todo").await;
+    assert_eq!(response.status, StatusCode::OK);
+    response.json()
+}
+
+async fn without_key(app: &TestApp, method: Method, uri: &str) -> StatusCode {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .body(Body::empty())
+        .unwrap();
+    app.send(request).await.status
+}
+
+#[tokio::test]
+async fn create_and_list() {
+    let app = TestApp::new();
+    assert!(list(&app).await.is_empty());
+
+    let response = app.post_json("/api/v1/todo", &todo("Buy milk")).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.headers[header::LOCATION], "/api/v1/todo/1");
+    let created: Todo = response.json();
+    assert_eq!(created.id, 1);
+    assert_eq!(created.value, "Buy milk");
+    assert!(!created.done);
+
+    app.post_json("/api/v1/todo", &todo("Walk dog")).await;
+    let ids: Vec<i32> = list(&app).await.iter().map(|todo| todo.id).collect();
+    assert_eq!(ids, [1, 2]);
+}
+
+#[tokio::test]
+async fn ids_are_not_reused() {
+    for state in [TodoState::new(), TodoState::open(":memory:").unwrap()] {
+        let app = TestApp::with_todos(state);
+        app.post_json("/api/v1/todo", &todo("Buy milk")).await;
+        app.post_json("/api/v1/todo", &todo("Walk dog")).await;
+        app.delete("/api/v1/todo/2").await;
+
+        let created: Todo = app
+            .post_json("/api/v1/todo", &todo("Feed cat"))
+            .await
+            .json();
+        assert_eq!(created.id, 3);
+    }
+}
+
+#[tokio::test]
+async fn create_rejects_id() {
+    let app = TestApp::new();
+    let response = app
+        .post_json(
+            "/api/v1/todo",
+            &serde_json::json!({"id": 7, "value": "Buy milk"}),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(list(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn list_pages() {
+    for state in [TodoState::new(), TodoState::open(":memory:").unwrap()] {
+        let app = TestApp::with_todos(state);
+        for value in ["a", "b", "c", "d", "e"] {
+            app.post_json("/api/v1/todo", &todo(value)).await;
+        }
+
+        let mut uri = String::from("/api/v1/todo?limit=2");
+        let mut pages = Vec::new();
+        loop {
+            let response = app.get(&uri).await;
+            assert_eq!(response.status, StatusCode::OK);
+            assert_eq!(response.headers[TOTAL_COUNT_HEADER], "5");
+            let page: Vec<Todo> = response.json();
+            pages.push(page.iter().map(|todo| todo.id).collect::<Vec<_>>());
+            let Some(link) = response.headers.get(header::LINK) else {
+                break;
+            };
+            let link = link.to_str().unwrap();
+            assert!(link.ends_with(r#">; rel="next""#), "{link}");
+            uri = String::from(&link[1..link.find('>').unwrap()]);
+        }
+        assert_eq!(pages, [vec![1, 2], vec![3, 4], vec![5]]);
+
+        // Deleting a todo does not shift the following pages.
+        app.delete("/api/v1/todo/3").await;
+        let page: Vec<Todo> = app.get("/api/v1/todo?cursor=2&limit=2").await.json();
+        let ids: Vec<i32> = page.iter().map(|todo| todo.id).collect();
+        assert_eq!(ids, [4, 5]);
+    }
+}
+
+#[tokio::test]
+async fn search() {
+    let app = TestApp::new();
+    app.post_json("/api/v1/todo", &todo("Buy milk")).await;
+    app.post_json("/api/v1/todo", &todo("Walk dog")).await;
+
+    let found: Vec<Todo> = app
+        .get("/api/v1/todo/search?value=buy%20MILK&done=false")
+        .await
+        .json();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].id, 1);
+
+    let found: Vec<Todo> = app
+        .get("/api/v1/todo/search?value=buy%20milk&done=true")
+        .await
+        .json();
+    assert!(found.is_empty());
+}
+
+async fn search_ids(app: &TestApp, query: &str) -> Vec<i32> {
+    let response = app.get(&format!("/api/v1/todo/search?{query}")).await;
+    assert_eq!(response.status, StatusCode::OK, "{query}");
+    let found: Vec<Todo> = response.json();
+    found.iter().map(|todo| todo.id).collect()
+}
+
+#[tokio::test]
+async fn search_modes() {
+    for state in [TodoState::new(), TodoState::open(":memory:").unwrap()] {
+        let app = TestApp::with_todos(state);
+        for value in ["Buy groceries", "Groceries list", "Walk dog"] {
+            app.post_json("/api/v1/todo", &todo(value)).await;
+        }
+        app.patch_json("/api/v1/todo/2", &json!({"done": true}))
+            .await;
+
+        assert_eq!(search_ids(&app, "").await, [1, 2, 3]);
+        assert_eq!(search_ids(&app, "value=groceries").await, [1, 2]);
+        assert_eq!(search_ids(&app, "value=GROCERIES&match=prefix").await, [2]);
+        assert!(search_ids(&app, "value=groceries&match=exact")
+            .await
+            .is_empty());
+        assert_eq!(
+            search_ids(&app, "value=%5E(buy%7Cwalk)&match=regex").await,
+            [1, 3]
+        );
+        assert_eq!(search_ids(&app, "value=groceries&done=false").await, [1]);
+        assert_eq!(search_ids(&app, "done=true").await, [2]);
+    }
+}
+
+#[tokio::test]
+async fn search_literal_text() {
+    for state in [TodoState::new(), TodoState::open(":memory:").unwrap()] {
+        let app = TestApp::with_todos(state);
+        for value in ["100% DONE", "1000 items", "ÄPFEL kaufen", "snake_case"] {
+            app.post_json("/api/v1/todo", &todo(value)).await;
+        }
+
+        assert_eq!(search_ids(&app, "value=100%25").await, [1]);
+        assert_eq!(search_ids(&app, "value=e_c").await, [4]);
+        assert!(search_ids(&app, "value=_&match=prefix").await.is_empty());
+        assert_eq!(search_ids(&app, "value=%C3%A4pfel&match=prefix").await, [3]);
+        assert_eq!(
+            search_ids(&app, "value=%C3%A4PFEL%20KAUFEN&match=exact").await,
+            [3]
+        );
+        assert_eq!(search_ids(&app, "value=%C3%A4pf&match=regex").await, [3]);
+        assert_eq!(
+            search_ids(&app, "sort=value&order=desc&offset=1&limit=2").await,
+            [4, 2]
+        );
+    }
+}
+
+#[tokio::test]
+async fn search_sort_and_page() {
+    let app = TestApp::new();
+    for value in ["b", "C", "a", "d"] {
+        app.post_json("/api/v1/todo", &todo(value)).await;
+    }
+
+    assert_eq!(search_ids(&app, "sort=value").await, [3, 1, 2, 4]);
+    assert_eq!(search_ids(&app, "sort=id&order=desc").await, [4, 3, 2, 1]);
+    assert_eq!(search_ids(&app, "sort=value&limit=2").await, [3, 1]);
+    assert_eq!(
+        search_ids(&app, "sort=value&limit=2&offset=2").await,
+        [2, 4]
+    );
+
+    for query in ["value=(&match=regex", "match=fuzzy", "limit=-1"] {
+        let response = app.get(&format!("/api/v1/todo/search?{query}")).await;
+        assert_eq!(response.status, StatusCode::BAD_REQUEST, "{query}");
+        assert!(matches!(response.json(), TodoError::InvalidInput(_)));
+    }
+}
+
+#[tokio::test]
+async fn patch() {
+    let app = TestApp::new();
+    app.post_json("/api/v1/todo", &todo("Buy milk")).await;
+
+    let response = app
+        .patch_json("/api/v1/todo/1", &json!({"done": true}))
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let updated: Todo = response.json();
+    assert_eq!((updated.value.as_str(), updated.done), ("Buy milk", true));
+
+    let updated: Todo = app
+        .patch_json("/api/v1/todo/1", &json!({"value": "Buy oat milk"}))
+        .await
+        .json();
+    assert_eq!(
+        (updated.value.as_str(), updated.done),
+        ("Buy oat milk", true)
+    );
+    assert_eq!(list(&app).await[0].value, "Buy oat milk");
+
+    let response = app
+        .patch_json("/api/v1/todo/7", &json!({"done": true}))
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert!(matches!(response.json(), TodoError::NotFound(_)));
+}
+
+#[tokio::test]
+async fn replace() {
+    let app = TestApp::new();
+    app.post_json("/api/v1/todo", &todo("Buy milk")).await;
+    app.patch_json("/api/v1/todo/1", &json!({"done": true}))
+        .await;
+
+    let update = TodoUpdate {
+        value: String::from("Walk dog"),
+        done: false,
+    };
+    let response = app.put_json("/api/v1/todo/1", &update).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let replaced: Todo = response.json();
+    assert_eq!((replaced.id, replaced.done), (1, false));
+    assert_eq!(replaced.value, "Walk dog");
+
+    let response = app
+        .put_json("/api/v1/todo/1", &json!({"value": "Walk dog"}))
+        .await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(matches!(response.json(), TodoError::InvalidInput(_)));
+
+    let response = app.put_json("/api/v1/todo/7", &update).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn rejects_invalid_input() {
+    let app = TestApp::new();
+    app.post_json("/api/v1/todo", &todo("Buy milk")).await;
+
+    for (method, uri, body) in [
+        (Method::POST, "/api/v1/todo", json!({"value": " "})),
+        (Method::PATCH, "/api/v1/todo/1", json!({"value": ""})),
+        (Method::PATCH, "/api/v1/todo/1", json!({"done": "yes"})),
+        (Method::PATCH, "/api/v1/todo/1", json!({"priority": 1})),
+    ] {
+        let response = app.send_json(method.clone(), uri, &body).await;
+        assert_eq!(
+            response.status,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "{method} {body}"
+        );
+        assert!(matches!(response.json(), TodoError::InvalidInput(_)));
+    }
+    assert_eq!(list(&app).await[0].value, "Buy milk");
+}
+
+#[tokio::test]
+async fn update_api_key_is_optional() {
+    let app = TestApp::new();
+    app.post_json("/api/v1/todo", &todo("Buy milk")).await;
+
+    for key in [None, Some("wrong")] {
+        let mut request = Request::builder()
+            .method(Method::PATCH)
+            .uri("/api/v1/todo/1")
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(key) = key {
+            request = request.header("theapikey", key);
+        }
+        let request = request.body(Body::from(r#"{"done": true}"#)).unwrap();
+        let expected = match key {
+            None => StatusCode::OK,
+            Some(_) => StatusCode::UNAUTHORIZED,
+        };
+        assert_eq!(app.send(request).await.status, expected);
+    }
+}
+
+#[tokio::test]
+async fn delete() {
+    let app = TestApp::new();
+    app.post_json("/api/v1/todo", &todo("Buy milk")).await;
+
+    assert_eq!(
+        without_key(&app, Method::DELETE, "/api/v1/todo/1").await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(list(&app).await.len(), 1);
+
+    assert_eq!(app.delete("/api/v1/todo/1").await.status, StatusCode::OK);
+    assert!(list(&app).await.is_empty());
+
+    let response = app.delete("/api/v1/todo/1").await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert!(matches!(response.json(), TodoError::NotFound(_)));
+}
+
+#[tokio::test]
+async fn persists_across_restart() {
+    let path = std::env::temp_dir().join(format!("todo-{}.sqlite", std::process::id()));
+    let app = TestApp::with_todos(TodoState::open(&path).unwrap());
+    app.post_json("/api/v1/todo", &todo("Buy milk")).await;
+    app.patch_json("/api/v1/todo/1", &json!({"done": true}))
+        .await;
+    drop(app);
+
+    let app = TestApp::with_todos(TodoState::open(&path).unwrap());
+    let todos = list(&app).await;
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(todos.len(), 1);
+    assert_eq!(todos[0].value, "Buy milk");
+    assert!(todos[0].done);
+}
+
+#[tokio::test]
+async fn opens_version_1_database() {
+    let path = std::env::temp_dir().join(format!("todo-v1-{}.sqlite", std::process::id()));
+    let connection = rusqlite::Connection::open(&path).unwrap();
+    connection
+        .execute_batch(
+            "CREATE TABLE todos (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 value TEXT NOT NULL,
+                 done INTEGER NOT NULL DEFAULT 0
+             );
+             INSERT INTO todos (value, done) VALUES ('Buy milk', 1), ('Walk dog', 0), ('Feed cat', 0);
+             DELETE FROM todos WHERE id = 3;
+             PRAGMA user_version = 1;",
+        )
+        .unwrap();
+    drop(connection);
+
+    let app = TestApp::with_todos(TodoState::open(&path).unwrap());
+    let todos = list(&app).await;
+    let created: Todo = app
+        .post_json("/api/v1/todo", &todo("Water plants"))
+        .await
+        .json();
+    drop(app);
+    let version: i64 = rusqlite::Connection::open(&path)
+        .unwrap()
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let todos: Vec<(i32, &str, bool)> = todos
+        .iter()
+        .map(|todo| (todo.id, todo.value.as_str(), todo.done))
+        .collect();
+    assert_eq!(todos, [(1, "Buy milk", true), (2, "Walk dog", false)]);
+    assert_eq!(created.id, 4);
+    assert_eq!(version, 1);
+}